@@ -0,0 +1,127 @@
+use std::path::{Path, PathBuf};
+
+const CONFIG_PATH: &str = "config.json5";
+const ROOT_ENV_VAR: &str = "CATA_ROOT";
+
+// places people tend to unpack the game into, relative to home and to drive roots
+const COMMON_INSTALL_DIRS: &[&str] = &[
+    "cataclysm-dda",
+    "Cataclysm-DDA",
+    "cdda",
+    "games/cataclysm-dda",
+    "Games/cataclysm-dda",
+    "games/Cataclysm-DDA",
+    "Games/Cataclysm-DDA",
+    ".local/share/cataclysm-dda",
+    "Applications/Cataclysm.app/Contents/Resources",
+];
+// launchers keep the actual game one or two levels deeper
+const LAUNCHER_SUBDIRS: &[&str] = &["cdda", "dda/current", "game", "current"];
+
+#[derive(Debug, Default, serde::Deserialize)]
+#[serde(deny_unknown_fields)]
+pub(crate) struct Config {
+    pub game_root: Option<PathBuf>,
+    #[serde(default)]
+    pub mods: Vec<String>,
+    pub lang: Option<String>,
+}
+
+impl Config {
+    pub(crate) fn load() -> Config {
+        let path = Path::new(CONFIG_PATH);
+        if !path.exists() {
+            return Config::default();
+        }
+        crate::read_input(path)
+    }
+}
+
+fn is_game_root(path: &Path) -> bool {
+    path.join("data").join("json").is_dir()
+}
+
+fn check_explicit_root(path: &Path, source: &str) -> Result<PathBuf, String> {
+    if is_game_root(path) {
+        Ok(path.to_owned())
+    } else {
+        Err(format!(
+            "game root {} (from {}) does not contain a data/json directory",
+            path.display(),
+            source
+        ))
+    }
+}
+
+fn search_roots() -> Vec<PathBuf> {
+    let mut bases = vec![];
+    if let Ok(cwd) = std::env::current_dir() {
+        bases.push(cwd.clone());
+        if let Some(parent) = cwd.parent() {
+            bases.push(parent.to_owned());
+        }
+    }
+    let home = std::env::var_os("HOME").or_else(|| std::env::var_os("USERPROFILE"));
+    let mut prefixes: Vec<PathBuf> = home.into_iter().map(PathBuf::from).collect();
+    if cfg!(windows) {
+        prefixes.extend(["C:\\", "D:\\", "E:\\"].iter().map(PathBuf::from));
+    } else {
+        prefixes.push("/opt".into());
+        prefixes.push("/usr/local/share".into());
+        prefixes.push("/usr/share".into());
+    }
+    for prefix in prefixes {
+        for dir in COMMON_INSTALL_DIRS {
+            bases.push(prefix.join(dir));
+        }
+    }
+
+    let mut candidates = vec![];
+    for base in bases {
+        if !base.is_dir() {
+            continue;
+        }
+        candidates.push(base.clone());
+        for sub in LAUNCHER_SUBDIRS {
+            candidates.push(base.join(sub));
+        }
+        // e.g. "launcher-autoupdated" or a per-version directory
+        if let Ok(entries) = std::fs::read_dir(&base) {
+            let mut children: Vec<PathBuf> = entries
+                .filter_map(|e| e.ok())
+                .map(|e| e.path())
+                .filter(|p| p.is_dir())
+                .collect();
+            children.sort();
+            candidates.extend(children);
+        }
+    }
+    candidates
+}
+
+// The game root comes from, in order of preference: the command line flag, the
+// `CATA_ROOT` environment variable, `game_root` in config.json5, and failing all of
+// those from a search through the usual install locations.
+pub(crate) fn find_game_root(flag: Option<&Path>, config: &Config) -> Result<PathBuf, String> {
+    if let Some(path) = flag {
+        return check_explicit_root(path, "--game-root");
+    }
+    if let Some(path) = std::env::var_os(ROOT_ENV_VAR) {
+        return check_explicit_root(Path::new(&path), ROOT_ENV_VAR);
+    }
+    if let Some(path) = &config.game_root {
+        return check_explicit_root(path, CONFIG_PATH);
+    }
+    let candidates = search_roots();
+    if let Some(found) = candidates.iter().find(|c| is_game_root(c)) {
+        log::info!("found game data in {}", found.display());
+        return Ok(found.clone());
+    }
+    Err(format!(
+        "could not find the game: none of the {} searched directories contains data/json. \
+        Pass --game-root, set {}, or put `game_root` into {}",
+        candidates.len(),
+        ROOT_ENV_VAR,
+        CONFIG_PATH
+    ))
+}
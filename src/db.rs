@@ -1,58 +1,184 @@
+mod copy_from;
+mod requirements;
+mod resolve;
+
+use crate::mods::ModSource;
 use crate::schema::{self, Material, Recipe, Requirement};
 use std::{
     collections::{HashMap, HashSet},
-    path::PathBuf,
+    path::{Path, PathBuf},
+    rc::Rc,
 };
 
+#[derive(serde::Serialize, serde::Deserialize)]
 pub(crate) struct Db {
     pub raw: Vec<serde_json::Value>,
     pub recipes: Vec<Recipe>,
     pub requirements: Vec<Requirement>,
     pub materials: Vec<Material>,
     pub itemgroups: Vec<schema::ItemGroup>,
+    pub proficiencies: Vec<schema::Proficiency>,
+    // whatever was skipped over in lenient mode
+    pub load_errors: Vec<DbError>,
+    index: DbIndex,
+    // depends on --lang rather than on the game data, so it isn't cached
+    #[serde(skip)]
+    item_names: std::cell::OnceCell<resolve::ItemNames>,
+}
+
+// Something in the game data we couldn't make sense of, and where it was.
+#[derive(Clone, Debug, serde::Serialize, serde::Deserialize)]
+pub(crate) struct DbError {
+    pub path: Option<PathBuf>,
+    pub typ: Option<String>,
+    pub id: Option<String>,
+    pub message: String,
 }
 
-#[allow(dead_code)]
-pub(crate) fn make_db_full() -> Db {
-    let db = load_db_flat(crate::CATA_ROOT);
-    Db {
-        recipes: parse_recipes(&db),
-        requirements: parse_requirements(&db),
-        materials: parse_materials(&db),
-        itemgroups: parse_itemgroups(&db),
-        raw: db,
+impl DbError {
+    fn in_file(path: &Path, message: impl std::fmt::Display) -> DbError {
+        DbError {
+            path: Some(path.to_owned()),
+            typ: None,
+            id: None,
+            message: message.to_string(),
+        }
+    }
+
+    fn in_entry(
+        origin: &Origin,
+        entry: &serde_json::Value,
+        message: impl std::fmt::Display,
+    ) -> DbError {
+        let id = if entry["type"] == "recipe" {
+            entry["result"]
+                .as_str()
+                .map(|result| match entry["id_suffix"].as_str() {
+                    Some(suffix) => format!("{} ({})", result, suffix),
+                    None => result.to_string(),
+                })
+        } else {
+            entry["id"]
+                .as_str()
+                .or_else(|| entry["abstract"].as_str())
+                .map(|s| s.to_string())
+        };
+        DbError {
+            path: Some(origin.to_path_buf()),
+            typ: entry["type"].as_str().map(|s| s.to_string()),
+            id,
+            message: message.to_string(),
+        }
     }
 }
 
-fn load_db_flat<P: AsRef<std::path::Path>>(cata_root: P) -> Vec<serde_json::Value> {
-    let json_root = cata_root.as_ref().join("data").join("json");
-
-    // only because we get id collisions
-    const BLACKLISTED_TYPES: &[&'static str] = &[
-        "ascii_art",
-        "ammo_effect",
-        "json_flag",
-        "clothing_mod",
-        "harvest",
-        "effect_type",
-        "MIGRATION",
-        "ammunition_type",
-        "vehicle_part",
-        "ITEM_CATEGORY",
-        "item_action",
-        "overmap_land_use_code",
-        "overmap_location",
-        "overmap_terrain",
-    ];
+impl std::fmt::Display for DbError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if let Some(path) = &self.path {
+            write!(f, "{}: ", path.display())?;
+        }
+        match (&self.typ, &self.id) {
+            (Some(typ), Some(id)) => write!(f, "{} {:?}: ", typ, id)?,
+            (Some(typ), None) => write!(f, "{}: ", typ)?,
+            (None, Some(id)) => write!(f, "{:?}: ", id)?,
+            (None, None) => {}
+        }
+        write!(f, "{}", self.message)
+    }
+}
+
+impl std::error::Error for DbError {}
+
+// the file an entry was read from
+type Origin = Rc<Path>;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum LoadMode {
+    // stop at the first bad entry
+    Strict,
+    // skip bad entries, listing them in `Db::load_errors`
+    Lenient,
+}
+
+struct LoadReport {
+    mode: LoadMode,
+    errors: Vec<DbError>,
+}
+
+impl LoadReport {
+    // In strict mode this hands the error back for `?` to abort with,
+    // otherwise it is noted down and the caller carries on without the entry.
+    fn skip(&mut self, error: DbError) -> Result<(), DbError> {
+        match self.mode {
+            LoadMode::Strict => Err(error),
+            LoadMode::Lenient => {
+                log::debug!("skipping {}", error);
+                self.errors.push(error);
+                Ok(())
+            }
+        }
+    }
+}
+
+pub(crate) fn make_db_full(sources: &[ModSource], mode: LoadMode) -> Result<Db, DbError> {
+    let mut report = LoadReport {
+        mode,
+        errors: vec![],
+    };
+    let entries = load_db_flat(sources, &mut report)?;
+    let (requirements, inliner) = parse_requirements(&entries, &mut report)?;
+    let recipes = parse_recipes(&entries, &inliner, &mut report)?;
+    let materials = parse_materials(&entries, &mut report)?;
+    let itemgroups = parse_itemgroups(&entries, &mut report)?;
+    let proficiencies = parse_entries(&entries, &mut report, |i| i["type"] == "proficiency")?;
+    let raw: Vec<serde_json::Value> = entries.into_iter().map(|(entry, _)| entry).collect();
+    let index = DbIndex::build(
+        &raw,
+        &recipes,
+        &requirements,
+        &materials,
+        &itemgroups,
+        &proficiencies,
+    );
+    Ok(Db {
+        raw,
+        recipes,
+        requirements,
+        materials,
+        itemgroups,
+        proficiencies,
+        load_errors: report.errors,
+        index,
+        item_names: Default::default(),
+    })
+}
+
+fn read_json_file(path: &Path) -> Result<serde_json::Value, DbError> {
+    let file = std::fs::File::open(path).map_err(|e| DbError::in_file(path, e))?;
+    serde_json::from_reader(std::io::BufReader::new(file)).map_err(|e| DbError::in_file(path, e))
+}
+
+fn read_json_dir(
+    json_root: &Path,
+    report: &mut LoadReport,
+) -> Result<Vec<(serde_json::Value, Origin)>, DbError> {
     // because speed; temporary; works even without
-    const WHITELISTED_TYPES: &[&'static str] = &["recipe", "requirement"];
-    const WHITELISTED_DIRS: &[&'static str] = &["recipes", "requirements"];
+    const WHITELISTED_TYPES: &[&str] = &["recipe", "requirement"];
+    const WHITELISTED_DIRS: &[&str] = &["recipes", "requirements"];
     const USE_WHITELIST: bool = false;
+    const BLACKLISTED_PLAIN_IDS: &[&str] = &["debug_backpack"];
 
     // read files
     let mut deserialized_raw = vec![];
     for entry in walkdir::WalkDir::new(json_root) {
-        let entry = entry.unwrap();
+        let entry = match entry {
+            Ok(entry) => entry,
+            Err(e) => {
+                let path = e.path().unwrap_or(json_root).to_owned();
+                report.skip(DbError::in_file(&path, e))?;
+                continue;
+            }
+        };
         let filename: PathBuf = entry.path().into();
         if filename.extension().map(|e| e.to_str()) != Some(Some("json")) {
             // println!("skipping {}", filename.display());
@@ -67,202 +193,218 @@ fn load_db_flat<P: AsRef<std::path::Path>>(cata_root: P) -> Vec<serde_json::Valu
             continue;
         }
         // debug!("reading {:?}", filename);
-        let file = std::io::BufReader::new(std::fs::File::open(&filename).unwrap());
-        let deserialized: serde_json::Value = serde_json::from_reader(file).unwrap();
+        let deserialized = match read_json_file(&filename) {
+            Ok(deserialized) => deserialized,
+            Err(e) => {
+                report.skip(e)?;
+                continue;
+            }
+        };
         let deserialized = match deserialized {
             serde_json::Value::Array(a) => a,
             x @ serde_json::Value::Object(..) => vec![x],
-            _ => unimplemented!(),
+            x => {
+                let message = format!("expected a list of entries, got {}", x);
+                report.skip(DbError::in_file(&filename, message))?;
+                continue;
+            }
         };
-        deserialized_raw.extend(
-            deserialized
-                .into_iter()
-                .filter(|des| !BLACKLISTED_TYPES.contains(&des["type"].as_str().unwrap()))
-                .filter(|des| des.get("obsolete").and_then(|x| x.as_bool()) != Some(true))
-                .filter(|des| {
-                    !USE_WHITELIST || WHITELISTED_TYPES.contains(&des["type"].as_str().unwrap())
-                })
-                .filter(|des| {
-                    const BLACKLISTED_RECIPE_IDS: &[(&str, Option<&str>)] = &[
-                        ("icecream_choc", Some("from_bags")),
-                        ("sleeveless_duster_faux_fur", None),
-                        ("crude_picklock", None), // this one is legit, but i don't want to support it yet
-                        ("crude_picklock", Some("from wire")),
-                    ];
-                    if des["type"] != "recipe" {
-                        return true;
-                    };
-                    !BLACKLISTED_RECIPE_IDS.contains(&(
-                        des["result"].as_str().unwrap(),
-                        des.get("id_suffix").and_then(|x| x.as_str()),
-                    ))
-                })
-                .filter(|des| {
-                    const BLACKLISTED_PLAIN_IDS: &[&str] = &["debug_backpack"];
-                    if let Some(id) = des.get("id").and_then(|id| id.as_str()) {
-                        if BLACKLISTED_PLAIN_IDS.contains(&id) {
-                            return false;
-                        }
-                    }
-                    true
-                }),
-        );
+        let origin: Origin = filename.into();
+        for des in deserialized {
+            let typ = match des["type"].as_str() {
+                Some(typ) => typ,
+                None => {
+                    report.skip(DbError::in_entry(&origin, &des, "entry has no \"type\""))?;
+                    continue;
+                }
+            };
+            if USE_WHITELIST && !WHITELISTED_TYPES.contains(&typ) {
+                continue;
+            }
+            if des.get("obsolete").and_then(|x| x.as_bool()) == Some(true) {
+                continue;
+            }
+            if let Some(id) = des.get("id").and_then(|id| id.as_str()) {
+                if BLACKLISTED_PLAIN_IDS.contains(&id) {
+                    continue;
+                }
+            }
+            deserialized_raw.push((des, origin.clone()));
+        }
     }
+    Ok(deserialized_raw)
+}
 
-    // water is in data/core/basic.json
-    deserialized_raw.push(serde_json::json!({
-        "type": "COMESTIBLE",
-        "id": "water",
-        "name": "water",
-        "description": "get me from data/core/basic.json",
-
-        "comestible_type": "DRINK",
-        "quench": 60,
-    }));
-    // build id->[indexes] mapping
-    let mut id_map = HashMap::<String, Vec<usize>>::new();
-    let mut abstract_map = HashMap::new();
-    let mut recipe_map = HashMap::<String, usize>::new();
-    for (index, entry) in deserialized_raw.iter().enumerate() {
-        if let Some(id) = entry["id"].as_str().map(|s| s.to_owned()) {
-            id_map.entry(id).or_default().push(index);
+// All the item types share one namespace, like they do in the game.
+// Every other type has its own, so e.g. a "harvest" can have the same id as an item.
+const ITEM_TYPES: &[&str] = &[
+    "GENERIC",
+    "ITEM",
+    "AMMO",
+    "GUN",
+    "GUNMOD",
+    "MAGAZINE",
+    "ARMOR",
+    "PET_ARMOR",
+    "TOOL",
+    "TOOLMOD",
+    "TOOL_ARMOR",
+    "BOOK",
+    "COMESTIBLE",
+    "CONTAINER",
+    "ENGINE",
+    "WHEEL",
+    "BIONIC_ITEM",
+    "BATTERY",
+];
+const ITEM_NAMESPACE: &str = "item";
+
+fn namespace(typ: &str) -> &str {
+    if ITEM_TYPES.contains(&typ) {
+        ITEM_NAMESPACE
+    } else {
+        typ
+    }
+}
+
+// What "copy-from" refers to an entry by, and what a later definition of it replaces.
+// Recipes go by what they make, with the suffix if any, everything else by id.
+fn namespaced_id(entry: &serde_json::Value) -> Option<(String, String)> {
+    let typ = entry["type"].as_str()?;
+    let id = if typ == "recipe" || typ == "uncraft" {
+        let result = entry["result"].as_str()?;
+        match entry["id_suffix"].as_str() {
+            Some(suffix) => format!("{}_{}", result, suffix),
+            None => result.to_string(),
         }
-        if let Some(abstract_id) = entry["abstract"].as_str().map(|s| s.to_owned()) {
-            abstract_map.insert(abstract_id, index);
+    } else {
+        entry["id"]
+            .as_str()
+            .or_else(|| entry["abstract"].as_str())?
+            .to_string()
+    };
+    Some((namespace(typ).to_string(), id))
+}
+
+fn load_db_flat(
+    sources: &[ModSource],
+    report: &mut LoadReport,
+) -> Result<Vec<(serde_json::Value, Origin)>, DbError> {
+    // mod_of[i] is the position in load order of the mod the i-th entry came from
+    let mut deserialized_raw = vec![];
+    let mut origins = vec![];
+    let mut mod_of = vec![];
+    for (mod_index, source) in sources.iter().enumerate() {
+        log::debug!("loading {} from {}", source.id, source.path.display());
+        let entries = read_json_dir(&source.path, report)?;
+        mod_of.extend(std::iter::repeat_n(mod_index, entries.len()));
+        for (entry, origin) in entries {
+            deserialized_raw.push(entry);
+            origins.push(origin);
         }
-        if let Some(result) = entry
-            .get("result")
-            .and_then(|x| x.as_str())
-            .map(|s| s.to_owned())
-        {
-            if entry.get("id_suffix").is_none() {
-                recipe_map.insert(result, index);
-            }
+    }
+
+    // build id->[indexes] mapping. Indexes are in load order, so later mods come last
+    let mut id_map = HashMap::<(String, String), Vec<usize>>::new();
+    for (index, entry) in deserialized_raw.iter().enumerate() {
+        if let Some(key) = namespaced_id(entry) {
+            id_map.entry(key).or_default().push(index);
         }
     }
 
-    // resolve copy-from
+    // resolve copy-from.
+    // Like the game does it, an entry only sees what was loaded by its own mod or before it,
+    // and the latest of those wins. That is also what makes a mod's "copy-from" of its own id
+    // build upon the definition it is overriding.
+    // `stack` is the entries being resolved right now, so that a cycle is an error.
     fn resolve_copy_from(
-        entry: &serde_json::Value,
+        index: usize,
         db: &[serde_json::Value],
-        id_map: &HashMap<String, Vec<usize>>,
-        abstract_map: &HashMap<String, usize>,
-        recipe_map: &HashMap<String, usize>,
-    ) -> serde_json::Value {
-        let entry_o = entry.as_object().unwrap();
-        let typ = entry_o["type"].as_str().unwrap().to_owned();
+        mod_of: &[usize],
+        id_map: &HashMap<(String, String), Vec<usize>>,
+        stack: &mut Vec<usize>,
+    ) -> Result<serde_json::Value, String> {
+        let entry = &db[index];
+        let entry_o = entry.as_object().ok_or("entry is not an object")?;
+        let typ = entry["type"].as_str().unwrap_or_default();
         let from_id = entry_o.get("copy-from");
         // return as is if no copy-from
         let from_id = match from_id {
             Some(x) => x,
-            None => return entry.clone(),
-        }
-        .as_str()
-        .unwrap();
-        // water is special
-        if from_id == "water" {
-            return entry.clone();
-        }
+            None => return Ok(entry.clone()),
+        };
+        let from_id = from_id
+            .as_str()
+            .ok_or_else(|| format!("\"copy-from\" should be a string, not {}", from_id))?;
         // println!("searching for {:?}", from_id);
 
-        let obj_index = if typ == "recipe" {
-            *recipe_map
-                .get(from_id)
-                .or_else(|| panic!("not found recipe {:?} for copy from", from_id))
-                .unwrap()
-        } else {
-            let obj_indexes: &[usize] = id_map.get(from_id).map(|x| x.as_ref()).unwrap_or(&[]);
-            let find_obj_index = || {
-                match obj_indexes.len() {
-                    0 => {
-                        return abstract_map
-                            .get(from_id)
-                            .ok_or_else(|| {
-                                format!(
-                                    "failed to find id {:?} for copy-from, while working on:\n{:?}",
-                                    from_id, entry
-                                )
-                            })
-                            .unwrap()
-                            .clone()
-                    }
-                    1 => return obj_indexes[0],
-                    _ => {}
-                };
-
-                let candidates = obj_indexes
-                    .iter()
-                    .map(|ind| (ind, &db[*ind]))
-                    .collect::<Vec<_>>();
-                let same_cat = candidates
-                    .iter()
-                    .filter(|(_ind, c)| c["type"] == typ)
-                    .collect::<Vec<_>>();
-                if let &[single] = same_cat.as_slice() {
-                    return *single.0;
-                }
-
-                if from_id == "bone" && typ == "GENERIC" {
-                    let bone = candidates
-                        .iter()
-                        .filter(|(_ind, c)| c["type"] == "material")
-                        .collect::<Vec<_>>();
-                    assert_eq!(bone.len(), 1);
-                    return *bone[0].0;
-                }
-                panic!(
-                    "too few/many candidates!\n{:?}\n out of\n{}\nsearching for {:?}\nin in{:?}",
-                    same_cat,
-                    {
-                        candidates
-                            .iter()
-                            .map(|x| format!("   - {:?}", x))
-                            .collect::<Vec<_>>()
-                            .join("\n")
-                    },
-                    from_id,
-                    typ,
-                );
-            };
-            let obj_index = find_obj_index();
-            obj_index
-        };
+        let key = (namespace(typ).to_string(), from_id.to_string());
+        let obj_index = id_map
+            .get(&key)
+            .into_iter()
+            .flatten()
+            .copied()
+            .rfind(|ind| *ind != index && mod_of[*ind] <= mod_of[index])
+            .ok_or_else(|| format!("no {} {:?} to copy from", key.0, from_id))?;
 
-        let base_obj = &db[obj_index];
-        let mut working_obj = resolve_copy_from(base_obj, db, id_map, abstract_map, recipe_map);
-        for key in vec!["copy-from", "id", "abstract"] {
-            working_obj.as_object_mut().unwrap().remove(key);
-        }
-        for (key, value) in entry_o.iter() {
-            working_obj
-                .as_object_mut()
-                .unwrap()
-                .insert(key.clone(), value.clone());
+        if let Some(start) = stack.iter().position(|ind| *ind == obj_index) {
+            let mut cycle: Vec<String> = stack[start..]
+                .iter()
+                .map(|ind| db[*ind]["id"].as_str().unwrap_or("?").to_string())
+                .collect();
+            cycle.push(from_id.to_string());
+            return Err(format!("copies from itself: {}", cycle.join(" -> ")));
         }
-
-        working_obj
+        stack.push(obj_index);
+        let base_obj = resolve_copy_from(obj_index, db, mod_of, id_map, stack);
+        stack.pop();
+        let base_obj = base_obj.map_err(|e| format!("copying from {:?}: {}", from_id, e))?;
+        copy_from::inherit(base_obj, entry_o)
     }
     let mut resolveds = Vec::with_capacity(deserialized_raw.len());
-    for entry in deserialized_raw.iter() {
-        let resolved = resolve_copy_from(
-            entry,
-            &deserialized_raw,
-            &id_map,
-            &abstract_map,
-            &recipe_map,
-        );
-        resolveds.push(resolved)
+    for index in 0..deserialized_raw.len() {
+        let mut stack = vec![index];
+        let resolved = resolve_copy_from(index, &deserialized_raw, &mod_of, &id_map, &mut stack);
+        match resolved {
+            Ok(resolved) => resolveds.push((resolved, origins[index].clone())),
+            Err(e) => report.skip(DbError::in_entry(
+                &origins[index],
+                &deserialized_raw[index],
+                e,
+            ))?,
+        }
+    }
+
+    // mods replace whatever was defined before them
+    let mut last_definition = HashMap::new();
+    for (index, (entry, _)) in resolveds.iter().enumerate() {
+        if let Some(key) = namespaced_id(entry) {
+            last_definition.insert(key, index);
+        }
     }
+    let mut resolveds: Vec<(serde_json::Value, Origin)> = resolveds
+        .into_iter()
+        .enumerate()
+        .filter(|(index, (entry, _))| match namespaced_id(entry) {
+            Some(key) => last_definition[&key] == *index,
+            None => true,
+        })
+        .map(|(_, entry)| entry)
+        .collect();
 
     // docs say that abstracts get removed after load, so let's remove them
-    resolveds.retain(|entry| entry.get("abstract").is_none());
+    resolveds.retain(|(entry, _)| entry.get("abstract").is_none());
 
-    resolveds
+    Ok(resolveds)
 }
 
-fn parse_recipes(db: &[serde_json::Value]) -> Vec<Recipe> {
+fn parse_recipes(
+    entries: &[(serde_json::Value, Origin)],
+    inliner: &requirements::Inliner,
+    report: &mut LoadReport,
+) -> Result<Vec<Recipe>, DbError> {
     let mut recipes = vec![];
-    for item in db {
+    for (item, origin) in entries {
         if item["type"] != "recipe" {
             continue;
         }
@@ -270,43 +412,13 @@ fn parse_recipes(db: &[serde_json::Value]) -> Vec<Recipe> {
         if item["category"] == "CC_BUILDING" {
             continue;
         }
-
-        let mut item = item.clone();
-        // normalize the "using" part first
-        let usings: Vec<schema::ComponentDesc> = match item.as_object_mut().unwrap().remove("using")
-        {
-            Some(using) => serde_json::from_value(using)
-                .map_err(|e| format!("the \"using\" part: {}: {}", e, item))
-                .unwrap(),
-            None => vec![],
-        };
-        let usings = usings.into_iter().map(|cd| {
-            use schema::ComponentDesc::*;
-            match cd {
-                Plain(s, i) => (s, i),
-                List(s, i) => (s, i),
-            }
-        });
-        let usings_as_component: Vec<Vec<(String, i32, &str)>> = usings
-            .into_iter()
-            .map(|(s, i)| vec![(s, i, "LIST")])
-            .collect();
-        let now_as_values = usings_as_component
-            .into_iter()
-            .map(|x| serde_json::to_value(x).unwrap());
-        item.as_object_mut()
-            .unwrap()
-            .entry("components")
-            .or_insert(serde_json::json!([]))
-            .as_array_mut()
-            .unwrap()
-            .extend(now_as_values);
-
-        let parsed: Recipe = serde_json::from_value(item.clone())
-            .map_err(|e| panic!("{}:\n{:#?}", e, item))
-            .unwrap();
-
-        recipes.push(parsed);
+        let parsed = serde_json::from_value(item.clone())
+            .map_err(|e| e.to_string())
+            .and_then(|mut recipe| inliner.inline_recipe(&mut recipe).map(|()| recipe));
+        match parsed {
+            Ok(recipe) => recipes.push(recipe),
+            Err(e) => report.skip(DbError::in_entry(origin, item, e))?,
+        }
     }
 
     // canned recipes are bad, mkay
@@ -327,186 +439,374 @@ fn parse_recipes(db: &[serde_json::Value]) -> Vec<Recipe> {
         recipes.retain(|r: &Recipe| !bads.contains(&(r.result.clone(), r.id_suffix.clone())));
     }
 
-    recipes
+    Ok(recipes)
+}
+
+// everything `wanted` picks out, as is
+fn parse_entries<T: serde::de::DeserializeOwned>(
+    entries: &[(serde_json::Value, Origin)],
+    report: &mut LoadReport,
+    wanted: impl Fn(&serde_json::Value) -> bool,
+) -> Result<Vec<T>, DbError> {
+    let mut parsed = vec![];
+    for (entry, origin) in entries.iter().filter(|(entry, _)| wanted(entry)) {
+        match serde_json::from_value(entry.clone()) {
+            Ok(p) => parsed.push(p),
+            Err(e) => report.skip(DbError::in_entry(origin, entry, e))?,
+        }
+    }
+    Ok(parsed)
 }
 
-fn parse_requirements(db: &[serde_json::Value]) -> Vec<Requirement> {
+// Requirements come back with the requirements they mention inlined,
+// together with what inlines them into recipes.
+fn parse_requirements(
+    entries: &[(serde_json::Value, Origin)],
+    report: &mut LoadReport,
+) -> Result<(Vec<Requirement>, requirements::Inliner), DbError> {
+    let mut parsed: Vec<(Requirement, &serde_json::Value, &Origin)> = vec![];
+    for (entry, origin) in entries.iter().filter(|(e, _)| e["type"] == "requirement") {
+        match serde_json::from_value(entry.clone()) {
+            Ok(requirement) => parsed.push((requirement, entry, origin)),
+            Err(e) => report.skip(DbError::in_entry(origin, entry, e))?,
+        }
+    }
+    let inliner = requirements::Inliner::new(parsed.iter().map(|(r, _, _)| r));
     let mut requirements = vec![];
-    for item in db {
-        if item["type"] != "requirement" {
-            continue;
+    for (requirement, entry, origin) in &parsed {
+        match inliner.requirement(&requirement.id) {
+            Ok(inlined) => requirements.push(inlined.clone()),
+            Err(e) => report.skip(DbError::in_entry(origin, entry, e))?,
         }
-        let parsed: Requirement = serde_json::from_value(item.clone())
-            .map_err(|e| panic!("on {}\ngot: {:?}", item.to_string(), e))
-            .unwrap();
-        requirements.push(parsed);
     }
-    requirements
+    Ok((requirements, inliner))
 }
 
-fn parse_materials(db: &[serde_json::Value]) -> Vec<schema::Material> {
-    let mats = db
-        .iter()
-        .filter(|i| i["type"] == "material")
-        .cloned()
-        .map(|i| {
-            serde_json::from_value(i.clone())
-                .map_err(|e| panic!("{}: {:?}", e, i))
-                .unwrap()
-        })
-        .collect();
-    mats
+fn parse_materials(
+    entries: &[(serde_json::Value, Origin)],
+    report: &mut LoadReport,
+) -> Result<Vec<schema::Material>, DbError> {
+    parse_entries(entries, report, |i| i["type"] == "material")
 }
 
-fn parse_itemgroups(db: &[serde_json::Value]) -> Vec<schema::ItemGroup> {
-    let mats = db
-        .iter()
-        .filter(|i| i["type"] == "item_group")
-        .filter(|i| i["subtype"] != serde_json::json!("collection"))
-        .cloned()
-        .map(|i| {
-            // println!("{}", serde_json::to_string_pretty(&i).unwrap());
-            serde_json::from_value(i).unwrap()
-        })
-        .collect();
-    mats
+fn parse_itemgroups(
+    entries: &[(serde_json::Value, Origin)],
+    report: &mut LoadReport,
+) -> Result<Vec<schema::ItemGroup>, DbError> {
+    parse_entries(entries, report, |i| {
+        i["type"] == "item_group" && i["subtype"] != serde_json::json!("collection")
+    })
+}
+
+// Everything the lookups need, built once at load time.
+// Values are indexes into the corresponding `Db` vectors.
+#[derive(Default, serde::Serialize, serde::Deserialize)]
+pub(crate) struct DbIndex {
+    // (namespace, id), see `namespaced_id`
+    raw_by_id: HashMap<(String, String), usize>,
+    recipes_by_id: HashMap<(String, Option<String>), usize>,
+    recipes_by_result: HashMap<String, Vec<usize>>,
+    requirements_by_id: HashMap<String, usize>,
+    materials_by_id: HashMap<String, usize>,
+    // item id -> groups listing it directly
+    itemgroups_by_item: HashMap<String, Vec<usize>>,
+    proficiencies_by_id: HashMap<String, usize>,
+}
+
+impl DbIndex {
+    fn build(
+        raw: &[serde_json::Value],
+        recipes: &[Recipe],
+        requirements: &[Requirement],
+        materials: &[Material],
+        itemgroups: &[schema::ItemGroup],
+        proficiencies: &[schema::Proficiency],
+    ) -> DbIndex {
+        let mut index = DbIndex::default();
+        for (i, entry) in raw.iter().enumerate() {
+            if let Some(key) = namespaced_id(entry) {
+                index.raw_by_id.insert(key, i);
+            }
+        }
+        for (i, rec) in recipes.iter().enumerate() {
+            index
+                .recipes_by_id
+                .insert((rec.result.clone(), rec.id_suffix.clone()), i);
+            index
+                .recipes_by_result
+                .entry(rec.result.clone())
+                .or_default()
+                .push(i);
+        }
+        for (i, req) in requirements.iter().enumerate() {
+            index.requirements_by_id.insert(req.id.clone(), i);
+        }
+        for (i, mat) in materials.iter().enumerate() {
+            index.materials_by_id.insert(mat.id.clone(), i);
+        }
+        for (i, group) in itemgroups.iter().enumerate() {
+            for (item, _) in &group.items {
+                if item.typ == schema::ItemGroupItemType::Item {
+                    let groups = index.itemgroups_by_item.entry(item.id.clone()).or_default();
+                    if groups.last() != Some(&i) {
+                        groups.push(i);
+                    }
+                }
+            }
+        }
+        for (i, proficiency) in proficiencies.iter().enumerate() {
+            index.proficiencies_by_id.insert(proficiency.id.clone(), i);
+        }
+        index
+    }
 }
 
 impl Db {
+    fn lookup_namespaced<'a>(&'a self, namespace: &str, id: &str) -> Option<&'a serde_json::Value> {
+        self.index
+            .raw_by_id
+            .get(&(namespace.to_string(), id.to_string()))
+            .map(|i| &self.raw[*i])
+    }
+
+    // any of the item types
     pub fn lookup_item<'a>(&'a self, id: &str) -> Option<&'a serde_json::Value> {
-        // is an item - not a requirement
-        let candidates = self
-            .raw
-            .iter()
-            .filter(|item| {
-                let mut ok = true;
-                let typ = item
-                    .as_object()
-                    .unwrap()
-                    .get("type")
-                    .unwrap()
-                    .as_str()
-                    .unwrap();
-
-                ok = ok && typ != "requirement";
-                ok = ok && typ != "material";
-                ok = ok && item.as_object().unwrap().get("id").and_then(|x| x.as_str()) == Some(id);
-                ok
-            })
-            .collect::<Vec<_>>();
-        assert!(candidates.len() <= 1, "{:?} - {:#?}", id, candidates);
-        candidates.get(0).cloned()
-    }
-
-    pub fn lookup_item_by_name<'a>(&'a self, want_name: &str) -> Option<&'a serde_json::Value> {
-        // is an item - not a requirement
-        let candidates = self
-            .raw
-            .iter()
-            .filter(|item| {
-                let mut ok = true;
-                ok = ok
-                    && item
-                        .as_object()
-                        .unwrap()
-                        .get("type")
-                        .unwrap()
-                        .as_str()
-                        .unwrap()
-                        != "requirement";
-                let item_name: Option<schema::Name> =
-                    serde_json::from_value(item["name"].clone()).ok();
-                ok = ok && item_name.map(|x| x.to_string()).as_deref() == Some(want_name);
-                ok
-            })
-            .collect::<Vec<_>>();
-        assert!(candidates.len() <= 1, "{:?} - {:#?}", want_name, candidates);
-        candidates.get(0).cloned()
+        self.lookup_namespaced(ITEM_NAMESPACE, id)
+    }
+
+    // `typ` being one of the item types finds an item of any type
+    pub fn lookup<'a>(&'a self, typ: &str, id: &str) -> Option<&'a serde_json::Value> {
+        self.lookup_namespaced(namespace(typ), id)
     }
 
     pub fn lookup_recipe<'a>(&'a self, result: &str, id_suffix: Option<&str>) -> &'a Recipe {
-        // is an item - not a requirement
-        let candidates = self
-            .recipes
-            .iter()
-            .filter(|rec| rec.result == result && rec.id_suffix.as_deref() == id_suffix)
-            .collect::<Vec<_>>();
-        assert!(candidates.len() == 1, "{:?} - {:#?}", result, candidates);
-        let item = candidates[0];
-        item
-    }
-
-    pub fn lookup_requirement<'a>(&'a self, id: &str) -> &'a Requirement {
-        // is an item - not a requirement
-        let candidates = self
-            .requirements
-            .iter()
-            .filter(|req| req.id == id)
-            .collect::<Vec<_>>();
-        assert!(candidates.len() == 1, "{:?} - {:#?}", id, candidates);
-        let item = candidates[0];
-        item
+        let key = (result.to_string(), id_suffix.map(|s| s.to_string()));
+        let index = self
+            .index
+            .recipes_by_id
+            .get(&key)
+            .unwrap_or_else(|| panic!("no recipe {:?}", key));
+        &self.recipes[*index]
+    }
+
+    pub fn find_requirement<'a>(&'a self, id: &str) -> Option<&'a Requirement> {
+        let index = self.index.requirements_by_id.get(id)?;
+        Some(&self.requirements[*index])
+    }
+
+    pub fn lookup_material<'a>(&'a self, id: &str) -> Option<&'a Material> {
+        self.index
+            .materials_by_id
+            .get(id)
+            .map(|i| &self.materials[*i])
+    }
+
+    pub fn lookup_proficiency<'a>(&'a self, id: &str) -> Option<&'a schema::Proficiency> {
+        self.index
+            .proficiencies_by_id
+            .get(id)
+            .map(|i| &self.proficiencies[*i])
+    }
+
+    // the item groups listing the item directly
+    pub fn itemgroups_with<'a>(&'a self, id: &str) -> impl Iterator<Item = &'a schema::ItemGroup> {
+        let indexes = self.index.itemgroups_by_item.get(id);
+        indexes
+            .into_iter()
+            .flatten()
+            .map(move |i| &self.itemgroups[*i])
+    }
+
+    pub fn recipes_for<'a>(&'a self, result: &str) -> impl Iterator<Item = &'a Recipe> {
+        let indexes = self.index.recipes_by_result.get(result);
+        indexes
+            .into_iter()
+            .flatten()
+            .map(move |i| &self.recipes[*i])
     }
 
     pub fn is_obtainable(&self, id: &str) -> bool {
-        for rec in self.recipes.iter() {
-            if rec.result == id {
-                return true;
-            }
+        self.index.recipes_by_result.contains_key(id)
+            || self.index.itemgroups_by_item.contains_key(id)
+    }
+}
+
+// one cache per set of mods
+fn compressed_path(sources: &[ModSource]) -> PathBuf {
+    let mut name = "db".to_string();
+    for source in sources.iter().filter(|s| !s.base) {
+        name.push('+');
+        name.push_str(&source.id);
+    }
+    Path::new("cache").join(name).with_extension("bin")
+}
+
+// bump whenever anything stored in `Db` changes shape
+const CACHE_FORMAT_VERSION: u32 = 13;
+
+const LOAD_REPORT_PATH: &str = "out/load_errors.txt";
+
+// What the cache was built from. Anything different means it is stale.
+#[derive(Debug, PartialEq, serde::Serialize, serde::Deserialize)]
+pub(crate) struct CacheKey {
+    game_version: Option<String>,
+    // of the contents of every json file that went into the db, with their paths
+    fingerprint: u64,
+}
+
+// 64-bit FNV-1a, which unlike `DefaultHasher` comes out the same with every build
+struct Fingerprint(u64);
+
+impl Fingerprint {
+    fn new() -> Fingerprint {
+        Fingerprint(0xcbf2_9ce4_8422_2325)
+    }
+
+    // length first, so that where one part ends and the next starts counts too
+    fn add(&mut self, bytes: &[u8]) {
+        let len = (bytes.len() as u64).to_le_bytes();
+        for byte in len.iter().chain(bytes) {
+            self.0 ^= *byte as u64;
+            self.0 = self.0.wrapping_mul(0x0000_0100_0000_01b3);
         }
-        for itemgroup in self.itemgroups.iter() {
-            if itemgroup
-                .items
-                .iter()
-                .any(|(item, _)| item.typ == schema::ItemGroupItemType::Item && item.id == id)
-            {
-                return true;
+    }
+}
+
+impl CacheKey {
+    pub(crate) fn compute(cata_root: &Path, sources: &[ModSource]) -> CacheKey {
+        let game_version = std::fs::read_to_string(cata_root.join("VERSION.txt"))
+            .ok()
+            .map(|v| v.trim().to_string());
+        let mut fingerprint = Fingerprint::new();
+        for source in sources {
+            fingerprint.add(source.id.as_bytes());
+            let files = walkdir::WalkDir::new(&source.path)
+                .sort_by(|a, b| a.file_name().cmp(b.file_name()));
+            for entry in files.into_iter().filter_map(|e| e.ok()) {
+                let filename = entry.path();
+                if filename.extension().map(|e| e.to_str()) != Some(Some("json")) {
+                    continue;
+                }
+                let relative = filename.strip_prefix(&source.path).unwrap();
+                let components: Vec<_> = relative
+                    .components()
+                    .map(|c| c.as_os_str().to_string_lossy())
+                    .collect();
+                fingerprint.add(components.join("/").as_bytes());
+                if let Ok(content) = std::fs::read(filename) {
+                    fingerprint.add(&content);
+                }
             }
         }
-        false
+        CacheKey {
+            game_version,
+            fingerprint: fingerprint.0,
+        }
     }
 }
 
-const COMPRESSED_PATH: &'static str = "cache/db.json";
-
-#[allow(dead_code)]
-pub(crate) fn dump_compressed(db: &Db) {
-    let out = std::fs::OpenOptions::new()
-        .create(true)
-        .truncate(true)
-        .write(true)
-        .open(COMPRESSED_PATH)
-        .unwrap();
-    serde_json::to_writer(out, &db.raw).unwrap();
+#[derive(serde::Serialize, serde::Deserialize)]
+struct CacheHeader {
+    format_version: u32,
+    key: CacheKey,
+}
+
+// The whole parsed `Db`, in messagepack since serde_json::Value and the untagged
+// schema enums need a self-describing format. The header goes first so that
+// a stale cache is noticed without reading the rest.
+pub(crate) fn dump_compressed(db: &Db, key: CacheKey, path: &Path) {
+    let mut out = std::io::BufWriter::new(crate::create_output(path));
+    let header = CacheHeader {
+        format_version: CACHE_FORMAT_VERSION,
+        key,
+    };
+    rmp_serde::encode::write_named(&mut out, &header).unwrap();
+    rmp_serde::encode::write_named(&mut out, db).unwrap();
+}
+
+pub(crate) fn load_compressed(path: &Path, key: &CacheKey) -> Option<Db> {
+    use serde::Deserialize;
+    let inp = std::io::BufReader::new(std::fs::OpenOptions::new().read(true).open(path).ok()?);
+    let mut de = rmp_serde::Deserializer::new(inp);
+    let header = match CacheHeader::deserialize(&mut de) {
+        Ok(h) => h,
+        Err(e) => {
+            println!("Cached db at {} is unreadable ({})", path.display(), e);
+            return None;
+        }
+    };
+    if header.format_version != CACHE_FORMAT_VERSION {
+        println!(
+            "Cached db has format version {}, want {}",
+            header.format_version, CACHE_FORMAT_VERSION
+        );
+        return None;
+    }
+    if &header.key != key {
+        println!(
+            "Cached db is stale: built from {:?}, now have {:?}",
+            header.key, key
+        );
+        return None;
+    }
+    match Db::deserialize(&mut de) {
+        Ok(db) => Some(db),
+        Err(e) => {
+            println!("Cached db at {} is unreadable ({})", path.display(), e);
+            None
+        }
+    }
 }
 
-#[allow(dead_code)]
-pub(crate) fn load_compressed() -> Option<Db> {
-    let inp = std::io::BufReader::new(
-        std::fs::OpenOptions::new()
-            .read(true)
-            .open(COMPRESSED_PATH)
-            .ok()?,
+fn write_load_report(errors: &[DbError]) {
+    use std::io::Write;
+    let path = Path::new(LOAD_REPORT_PATH);
+    let mut out = std::io::BufWriter::new(crate::create_output(path));
+    for error in errors {
+        writeln!(out, "{}", error).unwrap();
+    }
+    println!(
+        "Skipped {} bad entries, see {}",
+        errors.len(),
+        path.display()
     );
-    let db: Vec<serde_json::Value> = serde_json::from_reader(inp).unwrap();
-    Some(Db {
-        recipes: parse_recipes(&db),
-        requirements: parse_requirements(&db),
-        materials: parse_materials(&db),
-        itemgroups: parse_itemgroups(&db),
-        raw: db,
-    })
 }
 
-#[allow(dead_code)]
-pub(crate) fn load_maybe_compressed() -> Db {
-    if let Some(c) = load_compressed() {
-        return c;
-    }
-    println!("Cached db does not exist. Rebuilding");
-    let base = make_db_full();
-    dump_compressed(&base);
-    println!("Cache rebuilt");
-    //
-    return load_compressed().unwrap();
+pub(crate) fn load_maybe_compressed(
+    cata_root: &Path,
+    sources: &[ModSource],
+    rebuild: bool,
+    mode: LoadMode,
+) -> Result<Db, DbError> {
+    let path = compressed_path(sources);
+    let key = CacheKey::compute(cata_root, sources);
+    let mut cached = None;
+    if rebuild {
+        println!("Rebuilding cached db as asked");
+    } else {
+        cached = load_compressed(&path, &key);
+    }
+    // a leniently built cache is missing whatever it skipped
+    if let Some(db) = &cached {
+        if mode == LoadMode::Strict && !db.load_errors.is_empty() {
+            println!("Cached db was built skipping bad entries, rebuilding strictly");
+            cached = None;
+        }
+    }
+    let db = match cached {
+        Some(db) => db,
+        None => {
+            if !rebuild {
+                println!("Rebuilding cached db");
+            }
+            let db = make_db_full(sources, mode)?;
+            dump_compressed(&db, key, &path);
+            println!("Cache rebuilt");
+            db
+        }
+    };
+    if !db.load_errors.is_empty() {
+        write_load_report(&db.load_errors);
+    }
+    Ok(db)
 }
@@ -0,0 +1,400 @@
+// Inheritance the way the game does it, see doc/JSON_INHERITANCE.md in the game repo.
+// The entry's own fields replace the inherited ones, then "relative" adds to them,
+// "proportional" multiplies them, "extend" appends to lists and "delete" removes from lists.
+use crate::schema::{Duration, Energy, Length, Volume, Weight};
+use serde_json::{Map, Value};
+
+const MODIFIERS: &[&str] = &["relative", "proportional", "extend", "delete"];
+
+// Strings with units, read and written the way the rest of the loading does it
+trait Quantity: std::str::FromStr + std::fmt::Display + std::ops::Add<Output = Self> + Sized {
+    fn scaled(self, factor: f64) -> Self;
+}
+
+macro_rules! impl_quantity {
+    ($($ty:ident),*) => {
+        $(impl Quantity for $ty {
+            fn scaled(self, factor: f64) -> $ty {
+                $ty::scaled(self, factor)
+            }
+        })*
+    };
+}
+impl_quantity!(Duration, Volume, Weight, Length, Energy);
+
+fn added<T: Quantity>(b: &str, d: &str) -> Option<String> {
+    Some((b.parse::<T>().ok()? + d.parse::<T>().ok()?).to_string())
+}
+
+fn multiplied<T: Quantity>(b: &str, factor: f64) -> Option<String> {
+    Some(b.parse::<T>().ok()?.scaled(factor).to_string())
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum QuantityKind {
+    Duration,
+    Volume,
+    Weight,
+    Length,
+    Energy,
+}
+
+const QUANTITY_KINDS: &[QuantityKind] = &[
+    QuantityKind::Duration,
+    QuantityKind::Volume,
+    QuantityKind::Weight,
+    QuantityKind::Length,
+    QuantityKind::Energy,
+];
+
+impl QuantityKind {
+    // What the units of the `key` field's value `b` make it. "m" is both minutes and meters,
+    // fields named like "longest_side" or "max_item_length" take it for meters.
+    fn of(key: &str, b: &str) -> Option<QuantityKind> {
+        let kinds: Vec<QuantityKind> = QUANTITY_KINDS
+            .iter()
+            .copied()
+            .filter(|kind| kind.multiply(b, 1.0).is_some())
+            .collect();
+        match kinds[..] {
+            [] => None,
+            [kind] => Some(kind),
+            _ if key.ends_with("side") || key.ends_with("length") => Some(QuantityKind::Length),
+            _ => Some(QuantityKind::Duration),
+        }
+    }
+
+    fn add(self, b: &str, d: &str) -> Option<String> {
+        match self {
+            QuantityKind::Duration => added::<Duration>(b, d),
+            QuantityKind::Volume => added::<Volume>(b, d),
+            QuantityKind::Weight => added::<Weight>(b, d),
+            QuantityKind::Length => added::<Length>(b, d),
+            QuantityKind::Energy => added::<Energy>(b, d),
+        }
+    }
+
+    fn multiply(self, b: &str, factor: f64) -> Option<String> {
+        match self {
+            QuantityKind::Duration => multiplied::<Duration>(b, factor),
+            QuantityKind::Volume => multiplied::<Volume>(b, factor),
+            QuantityKind::Weight => multiplied::<Weight>(b, factor),
+            QuantityKind::Length => multiplied::<Length>(b, factor),
+            QuantityKind::Energy => multiplied::<Energy>(b, factor),
+        }
+    }
+}
+
+fn number_value(v: f64, keep_integer: bool) -> Value {
+    if keep_integer {
+        // the game stores these into ints, which truncates
+        Value::from(v.trunc() as i64)
+    } else {
+        serde_json::Number::from_f64(v)
+            .map(Value::Number)
+            .unwrap_or(Value::Null)
+    }
+}
+
+// [["CUT", 1], ["HAMMER", 2]] style lists are keyed on the first element
+fn list_key(v: &Value) -> Option<&str> {
+    match v {
+        Value::String(s) => Some(s),
+        Value::Array(a) => a.first().and_then(|x| x.as_str()),
+        Value::Object(o) => o.get("id").and_then(|x| x.as_str()),
+        _ => None,
+    }
+}
+
+// `key` is the field being added to, for telling what kind of quantity a string is
+fn add_relative(key: &str, base: Option<&Value>, delta: &Value) -> Result<Value, String> {
+    Ok(match (base, delta) {
+        (None, d) => d.clone(),
+        (Some(Value::Number(b)), Value::Number(d)) => {
+            let keep_integer = b.is_i64() && d.is_i64();
+            number_value(b.as_f64().unwrap() + d.as_f64().unwrap(), keep_integer)
+        }
+        (Some(Value::String(b)), d) => {
+            let kind = match QuantityKind::of(key, b) {
+                Some(kind) => kind,
+                // what says which part is being added to, like a damage_type
+                None if d.as_str() == Some(b) => return Ok(d.clone()),
+                None => return Err(format!("can't add {} to {:?}, not a quantity", d, b)),
+            };
+            let d = match d {
+                Value::String(d) => d.clone(),
+                // a bare number is in the base's own unit, the last one if there are several
+                Value::Number(n) => {
+                    let b = b.trim_end();
+                    format!(
+                        "{} {}",
+                        n,
+                        &b[b.trim_end_matches(char::is_alphabetic).len()..]
+                    )
+                }
+                _ => return Err(format!("can't add {} to {:?}", d, b)),
+            };
+            match kind.add(b, &d) {
+                Some(sum) => sum.into(),
+                None => return Err(format!("can't add {:?} to {:?}", d, b)),
+            }
+        }
+        (Some(Value::Object(b)), Value::Object(d)) => {
+            let mut out = b.clone();
+            for (key, d) in d {
+                let added = add_relative(key, b.get(key), d)?;
+                out.insert(key.clone(), added);
+            }
+            Value::Object(out)
+        }
+        (Some(Value::Array(b)), Value::Array(d)) => {
+            let mut out = b.clone();
+            for d in d {
+                let position = out
+                    .iter()
+                    .position(|x| list_key(x).is_some() && list_key(x) == list_key(d));
+                match (position.map(|pos| &mut out[pos]), d) {
+                    (Some(Value::Array(b_pair)), Value::Array(pair)) if pair.len() == 2 => {
+                        b_pair[1] = add_relative(key, b_pair.get(1), &pair[1])?;
+                    }
+                    _ => out.push(d.clone()),
+                }
+            }
+            Value::Array(out)
+        }
+        (Some(b), d) => return Err(format!("can't add {} to {}", d, b)),
+    })
+}
+
+fn apply_proportional(
+    key: &str,
+    base: Option<&Value>,
+    factor: &Value,
+) -> Result<Option<Value>, String> {
+    let base = match base {
+        Some(b) => b,
+        None => return Ok(None),
+    };
+    Ok(Some(match (base, factor) {
+        (Value::Number(b), Value::Number(f)) => {
+            number_value(b.as_f64().unwrap() * f.as_f64().unwrap(), b.is_i64())
+        }
+        (Value::String(b), Value::Number(f)) => {
+            match QuantityKind::of(key, b).and_then(|kind| kind.multiply(b, f.as_f64().unwrap())) {
+                Some(product) => product.into(),
+                None => return Err(format!("can't multiply {:?} by {}", b, f)),
+            }
+        }
+        (Value::Object(b), Value::Object(f)) => {
+            let mut out = b.clone();
+            for (key, f) in f {
+                if let Some(multiplied) = apply_proportional(key, b.get(key), f)? {
+                    out.insert(key.clone(), multiplied);
+                }
+            }
+            Value::Object(out)
+        }
+        (b, f) => return Err(format!("can't multiply {} by {}", b, f)),
+    }))
+}
+
+fn as_list(v: Option<&Value>) -> Vec<Value> {
+    match v {
+        None | Some(Value::Null) => vec![],
+        Some(Value::Array(a)) => a.clone(),
+        Some(x) => vec![x.clone()],
+    }
+}
+
+fn apply_extend(base: Option<&Value>, extension: &Value) -> Value {
+    match (base, extension) {
+        (Some(Value::Object(b)), Value::Object(e)) => {
+            let mut out = b.clone();
+            for (key, e) in e {
+                let extended = apply_extend(b.get(key), e);
+                out.insert(key.clone(), extended);
+            }
+            Value::Object(out)
+        }
+        (b, e) => {
+            let mut out = as_list(b);
+            for e in as_list(Some(e)) {
+                // flags and friends are sets in the game
+                if !out.contains(&e) {
+                    out.push(e);
+                }
+            }
+            Value::Array(out)
+        }
+    }
+}
+
+fn apply_delete(base: Option<&Value>, deletion: &Value) -> Option<Value> {
+    let base = base?;
+    Some(match (base, deletion) {
+        (Value::Object(b), Value::Object(d)) => {
+            let mut out = b.clone();
+            for (key, d) in d {
+                if let Some(deleted) = apply_delete(b.get(key), d) {
+                    out.insert(key.clone(), deleted);
+                }
+            }
+            Value::Object(out)
+        }
+        (b, d) => {
+            let to_delete = as_list(Some(d));
+            let mut out = as_list(Some(b));
+            out.retain(|x| {
+                !to_delete
+                    .iter()
+                    .any(|d| d == x || (d.is_string() && list_key(x) == d.as_str()))
+            });
+            Value::Array(out)
+        }
+    })
+}
+
+// `base` is the already resolved entry being copied from
+pub(super) fn inherit(base: Value, entry: &Map<String, Value>) -> Result<Value, String> {
+    let mut out = match base {
+        Value::Object(o) => o,
+        x => return Err(format!("can only copy from objects, not {}", x)),
+    };
+    for key in ["copy-from", "id", "abstract"] {
+        out.remove(key);
+    }
+    for (key, value) in entry.iter() {
+        if !MODIFIERS.contains(&key.as_str()) {
+            out.insert(key.clone(), value.clone());
+        }
+    }
+
+    let modifier = |name: &str| entry.get(name).and_then(|m| m.as_object());
+    if let Some(relative) = modifier("relative") {
+        for (key, delta) in relative {
+            let added = add_relative(key, out.get(key), delta)?;
+            out.insert(key.clone(), added);
+        }
+    }
+    if let Some(proportional) = modifier("proportional") {
+        for (key, factor) in proportional {
+            if let Some(multiplied) = apply_proportional(key, out.get(key), factor)? {
+                out.insert(key.clone(), multiplied);
+            }
+        }
+    }
+    if let Some(extend) = modifier("extend") {
+        for (key, extension) in extend {
+            let extended = apply_extend(out.get(key), extension);
+            out.insert(key.clone(), extended);
+        }
+    }
+    if let Some(delete) = modifier("delete") {
+        for (key, deletion) in delete {
+            if let Some(deleted) = apply_delete(out.get(key), deletion) {
+                out.insert(key.clone(), deleted);
+            }
+        }
+    }
+    Ok(Value::Object(out))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // each fixture is {"base": .., "entry": .., "expected": ..}
+    fn check_fixture(name: &str) {
+        let path = std::path::Path::new(env!("CARGO_MANIFEST_DIR"))
+            .join("tests/fixtures/copy_from")
+            .join(name)
+            .with_extension("json");
+        let text = std::fs::read_to_string(&path).unwrap();
+        let fixture: Value = serde_json::from_str(&text).unwrap();
+        let inherited = inherit(
+            fixture["base"].clone(),
+            fixture["entry"].as_object().unwrap(),
+        )
+        .unwrap();
+        assert_eq!(
+            inherited,
+            fixture["expected"],
+            "{}:\n{:#}",
+            path.display(),
+            inherited
+        );
+    }
+
+    #[test]
+    fn plain_override() {
+        check_fixture("plain_override");
+    }
+
+    #[test]
+    fn relative_numbers() {
+        check_fixture("relative_numbers");
+    }
+
+    #[test]
+    fn relative_units() {
+        check_fixture("relative_units");
+    }
+
+    #[test]
+    fn relative_length() {
+        check_fixture("relative_length");
+    }
+
+    #[test]
+    fn relative_nested() {
+        check_fixture("relative_nested");
+    }
+
+    #[test]
+    fn proportional() {
+        check_fixture("proportional");
+    }
+
+    #[test]
+    fn extend() {
+        check_fixture("extend");
+    }
+
+    #[test]
+    fn delete() {
+        check_fixture("delete");
+    }
+
+    #[test]
+    fn quantities() {
+        let add = |b: &str, d: Value| add_relative("x", Some(&Value::from(b)), &d);
+        assert_eq!(add("1 h 30 m", "10 m".into()), Ok("1 h 40 m".into()));
+        assert_eq!(add("90m", 30.into()), Ok("2 h".into()));
+        assert_eq!(add("1 L", "250 ml".into()), Ok("1.25 L".into()));
+        assert_eq!(add("2 kJ", "-500 J".into()), Ok("1.5 kJ".into()));
+        assert!(add("1 L", "1 kg".into()).is_err());
+        assert!(add("lots", "1 L".into()).is_err());
+        assert_eq!(add("bullet", "bullet".into()), Ok("bullet".into()));
+
+        // "m" goes by the field unless the other units settle it
+        assert!(add("1 m", "50 cm".into()).is_err());
+        assert_eq!(
+            add("1 h", "50 cm".into()),
+            Err("can't add \"50 cm\" to \"1 h\"".into())
+        );
+        assert_eq!(
+            QuantityKind::of("longest_side", "2 m"),
+            Some(QuantityKind::Length)
+        );
+        assert_eq!(
+            QuantityKind::of("longest_side", "2 h"),
+            Some(QuantityKind::Duration)
+        );
+        assert_eq!(
+            QuantityKind::of("time", "2 m"),
+            Some(QuantityKind::Duration)
+        );
+        let multiplied = QuantityKind::Duration.multiply("1 h", 1.5);
+        assert_eq!(multiplied, Some("1 h 30 m".to_string()));
+    }
+}
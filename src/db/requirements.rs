@@ -0,0 +1,228 @@
+// Requirements folded into whatever refers to them, the way the game does it:
+// "using": [[id, n]] brings in all of a requirement's components, tools and qualities n times over,
+// and a LIST alternative stands for the alternatives of a requirement with a single group.
+// Afterwards neither recipes nor requirements mention other requirements.
+use crate::schema::{ComponentDesc, ComponentKind, Quality, Recipe, Requirement};
+use std::collections::HashMap;
+
+type Groups = Vec<Vec<ComponentDesc>>;
+
+pub(super) struct Inliner {
+    // fully inlined, or why that didn't work out
+    requirements: HashMap<String, Result<Requirement, String>>,
+    // the requirements each one's LISTs brought in, however deep
+    includes: HashMap<String, Vec<String>>,
+}
+
+fn scale_component(component: &ComponentDesc, times: i32) -> ComponentDesc {
+    ComponentDesc {
+        amount: component.amount * times,
+        ..component.clone()
+    }
+}
+
+// tools without charges are needed once however many times over
+fn scale_tool(tool: &ComponentDesc, times: i32) -> ComponentDesc {
+    ComponentDesc {
+        amount: tool.charges().map_or(tool.amount, |c| c * times),
+        ..tool.clone()
+    }
+}
+
+fn merge_qualities(into: &mut Vec<Quality>, from: &[Quality]) {
+    for quality in from {
+        match into.iter_mut().find(|q| q.id == quality.id) {
+            Some(known) => known.level = known.level.max(quality.level),
+            None => into.push(quality.clone()),
+        }
+    }
+}
+
+impl Inliner {
+    pub(super) fn new<'a>(requirements: impl Iterator<Item = &'a Requirement>) -> Inliner {
+        let requirements: Vec<&Requirement> = requirements.collect();
+        let by_id: HashMap<&str, &Requirement> =
+            requirements.iter().map(|r| (r.id.as_str(), *r)).collect();
+        let mut inliner = Inliner {
+            requirements: HashMap::new(),
+            includes: HashMap::new(),
+        };
+        // in load order, so that which one a cycle is blamed on doesn't change between runs
+        for requirement in requirements {
+            inliner.inline_requirement(&requirement.id, &by_id, &mut vec![]);
+        }
+        inliner
+    }
+
+    pub(super) fn requirement(&self, id: &str) -> Result<&Requirement, String> {
+        match self.requirements.get(id) {
+            Some(Ok(requirement)) => Ok(requirement),
+            Some(Err(e)) => Err(e.clone()),
+            None => Err(format!("no requirement {:?}", id)),
+        }
+    }
+
+    // the same, as seen from something including it
+    fn included(&self, id: &str) -> Result<&Requirement, String> {
+        match self.requirements.get(id) {
+            Some(Err(e)) => Err(format!("requirement {:?}: {}", id, e)),
+            _ => self.requirement(id),
+        }
+    }
+
+    // `stack` is the requirements being inlined right now, to catch them including themselves
+    fn inline_requirement(
+        &mut self,
+        id: &str,
+        by_id: &HashMap<&str, &Requirement>,
+        stack: &mut Vec<String>,
+    ) {
+        if self.requirements.contains_key(id) {
+            return;
+        }
+        let requirement = match by_id.get(id) {
+            Some(r) => *r,
+            None => return,
+        };
+        if stack.iter().any(|s| s == id) {
+            let error = format!("includes itself: {}", stack.join(" -> "));
+            self.requirements.insert(id.to_string(), Err(error));
+            return;
+        }
+        stack.push(id.to_string());
+        let nested = requirement.components.iter().chain(&requirement.tools);
+        for desc in nested.flatten() {
+            if desc.kind == ComponentKind::List {
+                self.inline_requirement(&desc.id, by_id, stack);
+            }
+        }
+        stack.pop();
+        let lists = Inliner::lists(&requirement.components, &requirement.tools);
+        let includes = self.with_includes(lists);
+        self.includes.insert(id.to_string(), includes);
+
+        let inlined =
+            self.inline_lists(&requirement.components, scale_component, |r| &r.components);
+        let inlined = inlined.and_then(|components| {
+            let tools = self.inline_lists(&requirement.tools, scale_tool, |r| &r.tools)?;
+            Ok(Requirement {
+                components,
+                tools,
+                ..requirement.clone()
+            })
+        });
+        // the cycle may have been noticed deeper down already
+        self.requirements.entry(id.to_string()).or_insert(inlined);
+    }
+
+    // replaces LIST alternatives with those of the (already inlined) requirement
+    fn inline_lists(
+        &self,
+        groups: &[Vec<ComponentDesc>],
+        scale: fn(&ComponentDesc, i32) -> ComponentDesc,
+        part: fn(&Requirement) -> &Groups,
+    ) -> Result<Groups, String> {
+        let mut inlined = vec![];
+        for group in groups {
+            let mut alternatives = vec![];
+            for desc in group {
+                if desc.kind == ComponentKind::Item {
+                    alternatives.push(desc.clone());
+                    continue;
+                }
+                let requirement = self.included(&desc.id)?;
+                match part(requirement).as_slice() {
+                    [only] => alternatives.extend(only.iter().map(|d| scale(d, desc.amount))),
+                    more => {
+                        return Err(format!(
+                            "LIST {:?} needs a requirement with a single group, it has {}",
+                            desc.id,
+                            more.len()
+                        ))
+                    }
+                }
+            }
+            inlined.push(alternatives);
+        }
+        Ok(inlined)
+    }
+
+    fn lists<'a>(
+        components: &'a [Vec<ComponentDesc>],
+        tools: &'a [Vec<ComponentDesc>],
+    ) -> Vec<&'a str> {
+        let descs = components.iter().chain(tools).flatten();
+        let lists = descs.filter(|desc| desc.kind == ComponentKind::List);
+        lists.map(|desc| desc.id.as_str()).collect()
+    }
+
+    // `ids` and whatever they include, each once
+    fn with_includes<'a>(&self, ids: impl IntoIterator<Item = &'a str>) -> Vec<String> {
+        let mut all: Vec<String> = vec![];
+        for id in ids {
+            let included = self.includes.get(id).into_iter().flatten();
+            for id in std::iter::once(id).chain(included.map(|s| s.as_str())) {
+                if !all.iter().any(|known| known == id) {
+                    all.push(id.to_string());
+                }
+            }
+        }
+        all
+    }
+
+    pub(super) fn inline_recipe(&self, recipe: &mut Recipe) -> Result<(), String> {
+        let lists = Inliner::lists(&recipe.components, &recipe.tools);
+        let using = recipe.using.iter().map(|(id, _)| id.as_str());
+        recipe.inlined_requirements = self.with_includes(lists.into_iter().chain(using));
+        recipe.components =
+            self.inline_lists(&recipe.components, scale_component, |r| &r.components)?;
+        recipe.tools = self.inline_lists(&recipe.tools, scale_tool, |r| &r.tools)?;
+        for (id, times) in &recipe.using {
+            let requirement = self.included(id)?;
+            let components = requirement.components.iter();
+            recipe.components.extend(
+                components.map(|group| group.iter().map(|c| scale_component(c, *times)).collect()),
+            );
+            let tools = requirement.tools.iter();
+            recipe
+                .tools
+                .extend(tools.map(|group| group.iter().map(|t| scale_tool(t, *times)).collect()));
+            merge_qualities(&mut recipe.qualities, &requirement.qualities);
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn inlined_requirements() {
+        let requirements: Vec<Requirement> = serde_json::from_value(serde_json::json!([
+            { "id": "milk_standard_raw", "components": [[["milk_raw", 1]]] },
+            { "id": "milk_standard", "components": [[["milk", 1], ["milk_standard_raw", 1, "LIST"]]] },
+            { "id": "sugar_standard", "components": [[["sugar", 1]]] },
+        ]))
+        .unwrap();
+        let inliner = Inliner::new(requirements.iter());
+        let mut recipe: Recipe = serde_json::from_value(serde_json::json!({
+            "result": "pudding",
+            "components": [[["milk_standard", 2, "LIST"]], [["egg", 1]]],
+            "using": [["sugar_standard", 1]],
+        }))
+        .unwrap();
+        inliner.inline_recipe(&mut recipe).unwrap();
+        let ids: Vec<&str> = recipe
+            .components
+            .iter()
+            .flatten()
+            .map(|c| c.id.as_str())
+            .collect();
+        assert_eq!(ids, ["milk", "milk_raw", "egg", "sugar"]);
+        assert_eq!(
+            recipe.inlined_requirements,
+            ["milk_standard", "milk_standard_raw", "sugar_standard"]
+        );
+    }
+}
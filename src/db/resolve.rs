@@ -0,0 +1,198 @@
+// Finding the item someone meant from what they typed into an input file:
+// an id, a name as the game shows it (singular or plural, English or translated),
+// the same ignoring case, and failing those the closest spelling.
+use super::{namespace, Db, ITEM_NAMESPACE};
+use crate::schema;
+use std::collections::{HashMap, HashSet};
+
+const MAX_SUGGESTIONS: usize = 5;
+
+// every spelling of every item's name -> indexes into `Db::raw`
+pub(super) struct ItemNames {
+    exact: HashMap<String, Vec<usize>>,
+    // lowercased
+    folded: HashMap<String, Vec<usize>>,
+}
+
+fn add_index(map: &mut HashMap<String, Vec<usize>>, key: String, index: usize) {
+    let indexes = map.entry(key).or_default();
+    if !indexes.contains(&index) {
+        indexes.push(index);
+    }
+}
+
+impl ItemNames {
+    fn build(raw: &[serde_json::Value]) -> ItemNames {
+        let mut names = ItemNames {
+            exact: HashMap::new(),
+            folded: HashMap::new(),
+        };
+        for (index, entry) in raw.iter().enumerate() {
+            if namespace(entry["type"].as_str().unwrap_or_default()) != ITEM_NAMESPACE {
+                continue;
+            }
+            let name: schema::Name = match serde_json::from_value(entry["name"].clone()) {
+                Ok(name) => name,
+                Err(_) => continue,
+            };
+            let spellings = [
+                name.as_str().to_string(),
+                name.plural(),
+                name.translated().to_string(),
+            ];
+            for spelling in spellings {
+                add_index(&mut names.folded, spelling.to_lowercase(), index);
+                add_index(&mut names.exact, spelling, index);
+            }
+        }
+        names
+    }
+}
+
+#[derive(Debug)]
+pub(crate) enum ResolveError {
+    NotFound {
+        query: String,
+        // closest first
+        suggestions: Vec<String>,
+    },
+    Ambiguous {
+        query: String,
+        candidates: Vec<String>,
+    },
+}
+
+impl std::fmt::Display for ResolveError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ResolveError::NotFound { query, suggestions } if suggestions.is_empty() => {
+                write!(f, "no item {:?}", query)
+            }
+            ResolveError::NotFound { query, suggestions } => write!(
+                f,
+                "no item {:?}, did you mean one of: {}",
+                query,
+                suggestions.join(", ")
+            ),
+            ResolveError::Ambiguous { query, candidates } => {
+                write!(f, "{:?} could be any of: {}", query, candidates.join(", "))
+            }
+        }
+    }
+}
+
+// What a query came to. A typo only comes to the closest spelling, which is worth telling about.
+pub(crate) struct Resolved<'a> {
+    pub item: &'a serde_json::Value,
+    // "taking .. to mean ..", if it was a guess
+    pub guess: Option<String>,
+}
+
+fn describe(entry: &serde_json::Value) -> String {
+    let name = serde_json::from_value::<schema::Name>(entry["name"].clone())
+        .map(|name| name.translated().to_string())
+        .unwrap_or_default();
+    format!(
+        "{} ({}, {})",
+        name,
+        entry["id"].as_str().unwrap_or_default(),
+        entry["type"].as_str().unwrap_or_default()
+    )
+}
+
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+    let mut current = vec![0; b.len() + 1];
+    for (i, ca) in a.chars().enumerate() {
+        current[0] = i + 1;
+        for (j, cb) in b.iter().enumerate() {
+            let substitution = previous[j] + if ca == *cb { 0 } else { 1 };
+            current[j + 1] = substitution.min(previous[j + 1] + 1).min(current[j] + 1);
+        }
+        std::mem::swap(&mut previous, &mut current);
+    }
+    previous[b.len()]
+}
+
+impl Db {
+    fn pick<'a>(
+        &'a self,
+        query: &str,
+        indexes: &[usize],
+    ) -> Result<&'a serde_json::Value, ResolveError> {
+        match indexes {
+            [index] => Ok(&self.raw[*index]),
+            _ => Err(ResolveError::Ambiguous {
+                query: query.to_string(),
+                candidates: indexes.iter().map(|i| describe(&self.raw[*i])).collect(),
+            }),
+        }
+    }
+
+    pub fn resolve_item<'a>(&'a self, query: &str) -> Result<Resolved<'a>, ResolveError> {
+        let sure = |item| Resolved { item, guess: None };
+        if let Some(item) = self.lookup_item(query) {
+            return Ok(sure(item));
+        }
+        let names = self.item_names.get_or_init(|| ItemNames::build(&self.raw));
+        if let Some(indexes) = names.exact.get(query) {
+            return self.pick(query, indexes).map(sure);
+        }
+        let folded = query.to_lowercase();
+        if let Some(indexes) = names.folded.get(&folded) {
+            return self.pick(query, indexes).map(sure);
+        }
+
+        // typos, in names and ids alike
+        // (edit distance, whether it doesn't even contain what was asked for, raw index)
+        let mut scored = vec![];
+        let mut score = |spelling: &str, index: usize| {
+            let distance = edit_distance(&folded, spelling);
+            scored.push((distance, !spelling.contains(&folded), index));
+        };
+        for (name, indexes) in &names.folded {
+            for index in indexes {
+                score(name, *index);
+            }
+        }
+        for ((namespace, id), index) in &self.index.raw_by_id {
+            if namespace == ITEM_NAMESPACE {
+                score(&id.to_lowercase(), *index);
+            }
+        }
+        scored.sort_unstable();
+        let mut seen = HashSet::new();
+        scored.retain(|(_, _, index)| seen.insert(*index));
+
+        // about one typo per five letters
+        let tolerance = (folded.chars().count() / 5).max(1);
+        if let Some((best, _, _)) = scored.first().filter(|(d, _, _)| *d <= tolerance) {
+            let closest: Vec<usize> = scored
+                .iter()
+                .take_while(|(d, _, _)| d == best)
+                .map(|(_, _, index)| *index)
+                .collect();
+            let picked = self.pick(query, &closest)?;
+            let guess = format!(
+                "no item {:?}, taking it to mean {}",
+                query,
+                describe(picked)
+            );
+            return Ok(Resolved {
+                item: picked,
+                guess: Some(guess),
+            });
+        }
+        // longer names containing the query are better guesses than short unrelated ones
+        scored.sort_by_key(|(distance, unrelated, _)| (*unrelated, *distance));
+        Err(ResolveError::NotFound {
+            query: query.to_string(),
+            suggestions: scored
+                .iter()
+                .take(MAX_SUGGESTIONS)
+                .map(|(_, _, index)| describe(&self.raw[*index]))
+                .collect(),
+        })
+    }
+}
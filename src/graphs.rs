@@ -1,53 +1,39 @@
+pub(crate) mod cost;
+pub(crate) mod proficiencies;
 pub(crate) mod train;
 
 use crate::db::Db;
-use crate::schema::{self, ComponentDesc, Recipe, Requirement};
+use crate::schema::{self, ComponentDesc, Recipe};
 use std::{
     collections::{HashMap, HashSet},
-    path::PathBuf,
+    path::{Path, PathBuf},
 };
 
-fn to_node(from: impl AsRef<str>, db: &Db) -> Node {
-    let f = from.as_ref();
-    if db.lookup_item(f).is_some() {
-        Node::Item(f.to_string())
-    } else if let Some(it) = db.lookup_item_by_name(f) {
-        let id = it["id"].as_str().unwrap();
-        Node::Item(id.to_string())
-    } else {
-        panic!("Failed to find item {:?}", f)
-    }
+fn to_node_list(from: &[String], db: &Db, input_path: &Path, field: &str) -> Vec<Node> {
+    crate::resolve_input(db, input_path, field, from)
+        .into_iter()
+        .map(Node::Item)
+        .collect()
 }
 
-fn to_node_list(from: &[impl AsRef<str>], db: &Db) -> Vec<Node> {
-    let mut nodes = vec![];
-    for f in from {
-        nodes.push(to_node(f, db))
-    }
-    nodes
+// Like `resolve_input`, but requirement ids are kept as they are, they filter by `uses_requirement`
+fn to_filter_list(from: &[String], db: &Db, input_path: &Path, field: &str) -> Vec<String> {
+    let (requirements, items): (Vec<String>, Vec<String>) = from
+        .iter()
+        .cloned()
+        .partition(|q| db.find_requirement(q).is_some());
+    let mut ids = crate::resolve_input(db, input_path, field, &items);
+    ids.extend(requirements);
+    ids
 }
 
 #[derive(Hash, PartialEq, Eq, Clone, Debug, PartialOrd, Ord)]
 enum Node {
     Item(String),
-    RequirementItem(String),
 }
 impl Node {
-    fn as_item(&self) -> Option<&String> {
-        match self {
-            Self::Item(i) => Some(i),
-            _ => None,
-        }
-    }
-    fn as_requirement_item(&self) -> Option<&String> {
-        match self {
-            Self::RequirementItem(i) => Some(i),
-            _ => None,
-        }
-    }
     fn name(&self) -> &String {
         match self {
-            Self::RequirementItem(i) => i,
             Self::Item(i) => i,
         }
     }
@@ -68,9 +54,6 @@ impl Node {
     fn from_recipe(rec: &Recipe) -> Node {
         Node::Item(rec.result.to_string())
     }
-    fn from_requirement(req: &Requirement) -> Node {
-        Node::RequirementItem(req.id.to_string())
-    }
 }
 impl Edge {
     fn from_recipe(node: &Node, rec: &Recipe) -> Edge {
@@ -80,42 +63,33 @@ impl Edge {
             recipe_suffix: rec.id_suffix.clone(),
         }
     }
-    fn from_requirement(node: &Node, req: &Requirement) -> Edge {
-        Edge {
-            source: node.clone(),
-            dest: Node::from_requirement(req),
-            recipe_suffix: None,
-        }
-    }
 }
+// requirements are inlined at load time, so components are always items
+fn component_node(desc: &ComponentDesc) -> Node {
+    Node::Item(desc.id.clone())
+}
+
 // returns true if at least one of the items is required in crafting
-fn has_mandatory_ingredient(item_ids: &[Node], components: &Vec<Vec<ComponentDesc>>) -> bool {
-    let plain_items: Vec<&String> = item_ids.iter().map(Node::as_item).flatten().collect();
-    let req_items: Vec<&String> = item_ids
-        .iter()
-        .map(Node::as_requirement_item)
-        .flatten()
-        .collect();
+fn has_mandatory_ingredient(item_ids: &[Node], components: &[Vec<ComponentDesc>]) -> bool {
     for component in components.iter() {
-        let all_bad = component.iter().all(|alternative: &ComponentDesc| -> bool {
-            match alternative {
-                ComponentDesc::Plain(s, _) => plain_items.contains(&s),
-                ComponentDesc::List(s, _) => req_items.contains(&s),
-            }
-        });
+        let all_bad = component
+            .iter()
+            .all(|alternative: &ComponentDesc| item_ids.contains(&component_node(alternative)));
         if all_bad {
             return true;
         }
     }
-    return false;
+    false
+}
+
+// whether any of `requirements` went into the recipe, see `Recipe::inlined_requirements`
+fn uses_requirement(recipe: &Recipe, requirements: &[String]) -> bool {
+    let mut inlined = recipe.inlined_requirements.iter();
+    inlined.any(|id| requirements.contains(id))
 }
 
 fn node_matches_component_desc(node: &Node, desc: &ComponentDesc) -> bool {
-    match (node, desc) {
-        (Node::Item(ni), ComponentDesc::Plain(nc, _)) => ni == nc,
-        (Node::RequirementItem(ni), ComponentDesc::List(nc, _)) => ni == nc,
-        _ => false,
-    }
+    *node == component_node(desc)
 }
 // iff using items provided is sufficient to satisfy the requirements,
 // then returns all the items usable in recipe
@@ -139,15 +113,248 @@ fn has_enough_components<'a>(
             return None;
         }
     }
-    return Some(usables);
+    Some(usables)
+}
+
+#[derive(Debug, serde::Deserialize)]
+#[serde(untagged)]
+enum OwnedTool {
+    Charged(String, i32),
+    // as many charges as it takes
+    Any(String),
+}
+
+// What there is to craft with, for checking recipe tools and qualities
+struct OwnedTools {
+    // id -> charges, None for as many as it takes
+    charges: HashMap<String, Option<i32>>,
+    // quality id -> best level
+    qualities: HashMap<String, i32>,
+}
+
+impl OwnedTools {
+    fn new(db: &Db, input_path: &Path, owned: &[OwnedTool]) -> OwnedTools {
+        let names: Vec<String> = owned
+            .iter()
+            .map(|tool| match tool {
+                OwnedTool::Charged(name, _) | OwnedTool::Any(name) => name.clone(),
+            })
+            .collect();
+        let ids = crate::resolve_input(db, input_path, "tools", &names);
+        let mut tools = OwnedTools {
+            charges: HashMap::new(),
+            qualities: HashMap::new(),
+        };
+        for (id, tool) in ids.into_iter().zip(owned) {
+            let item = db.lookup_item(&id).unwrap();
+            let item_qualities: Vec<(String, i32)> =
+                serde_json::from_value(item["qualities"].clone()).unwrap_or_default();
+            for (quality, level) in item_qualities {
+                let best = tools.qualities.entry(quality).or_insert(level);
+                *best = (*best).max(level);
+            }
+            let charges = match tool {
+                OwnedTool::Charged(_, charges) => Some(*charges),
+                OwnedTool::Any(_) => None,
+            };
+            tools.charges.insert(id, charges);
+        }
+        tools
+    }
+
+    fn has_tool(&self, tool: &ComponentDesc) -> bool {
+        match (self.charges.get(&tool.id), tool.charges()) {
+            (None, _) => false,
+            (Some(Some(have)), Some(need)) => *have >= need,
+            (Some(_), _) => true,
+        }
+    }
+
+    fn can_craft(&self, recipe: &Recipe) -> bool {
+        let tools_ok = recipe
+            .tools
+            .iter()
+            .all(|group| group.iter().any(|tool| self.has_tool(tool)));
+        let qualities_ok = recipe.qualities.iter().all(|quality| {
+            self.qualities
+                .get(&quality.id)
+                .is_some_and(|level| *level >= quality.level)
+        });
+        tools_ok && qualities_ok
+    }
+}
+
+// How a recipe comes to be known
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum KnownBy {
+    Autolearn,
+    Book(String),
+    Disassembly,
+}
+
+impl std::fmt::Display for KnownBy {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            KnownBy::Autolearn => write!(f, "autolearn"),
+            KnownBy::Book(book) => write!(f, "from {}", book),
+            KnownBy::Disassembly => write!(f, "from taking one apart"),
+        }
+    }
+}
+
+// A proficiency a recipe wants that the character doesn't have, and what going without costs
+struct MissingProficiency<'a> {
+    id: &'a str,
+    required: bool,
+    time_multiplier: f32,
+    fail_multiplier: f32,
+}
+
+impl std::fmt::Display for MissingProficiency<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.required {
+            return write!(f, "{} required", self.id);
+        }
+        write!(
+            f,
+            "{} missing: x{} time, x{} failures",
+            self.id, self.time_multiplier, self.fail_multiplier
+        )
+    }
+}
+
+// What the character knows, for checking which recipes they have
+struct Knowledge<'a> {
+    // for the proficiencies' default penalties
+    db: &'a Db,
+    skills: HashMap<String, i32>,
+    // ids of the books owned
+    books: Vec<String>,
+    // ids of the items taken apart already, whose recipes that can teach
+    disassembled: Vec<String>,
+    proficiencies: Vec<String>,
+    // leave out recipes missing any proficiency, rather than only those requiring one
+    strict_proficiencies: bool,
+}
+
+impl<'a> Knowledge<'a> {
+    fn new(
+        db: &'a Db,
+        input_path: &Path,
+        skills: &HashMap<String, i32>,
+        books: &[String],
+        disassembled: &[String],
+        proficiencies: &[String],
+        strict_proficiencies: bool,
+    ) -> Knowledge<'a> {
+        for id in proficiencies {
+            if db.lookup_proficiency(id).is_none() {
+                eprintln!(
+                    "error: {}: proficiencies: no proficiency {:?}",
+                    input_path.display(),
+                    id
+                );
+                std::process::exit(1)
+            }
+        }
+        Knowledge {
+            db,
+            skills: skills.clone(),
+            books: crate::resolve_input(db, input_path, "books", books),
+            disassembled: crate::resolve_input(db, input_path, "disassembled", disassembled),
+            proficiencies: proficiencies.to_vec(),
+            strict_proficiencies,
+        }
+    }
+
+    fn missing_proficiencies<'r>(&self, recipe: &'r Recipe) -> Vec<MissingProficiency<'r>> {
+        let missing = recipe
+            .proficiencies
+            .iter()
+            .filter(|p| !self.proficiencies.contains(&p.proficiency));
+        missing
+            .map(|p| {
+                let definition = self.db.lookup_proficiency(&p.proficiency);
+                MissingProficiency {
+                    id: &p.proficiency,
+                    required: p.required,
+                    time_multiplier: p
+                        .time_multiplier
+                        .or(definition.map(|d| d.default_time_multiplier))
+                        .unwrap_or(1.0),
+                    fail_multiplier: p
+                        .fail_multiplier
+                        .or(definition.map(|d| d.default_fail_multiplier))
+                        .unwrap_or(1.0),
+                }
+            })
+            .collect()
+    }
+
+    // how much slower the character crafts it, None if they can't
+    fn time_multiplier(&self, recipe: &Recipe) -> Option<f64> {
+        let mut multiplier = 1.0;
+        for missing in self.missing_proficiencies(recipe) {
+            if missing.required || self.strict_proficiencies {
+                return None;
+            }
+            multiplier *= missing.time_multiplier as f64;
+        }
+        Some(multiplier)
+    }
+
+    // known and not out of reach for lack of proficiencies
+    fn can_craft(&self, recipe: &Recipe) -> bool {
+        self.known_by(recipe).is_some() && self.time_multiplier(recipe).is_some()
+    }
+
+    fn level(&self, skill: &str) -> i32 {
+        self.skills.get(skill).copied().unwrap_or(0)
+    }
+
+    fn has_levels(&self, levels: &[(String, i32)]) -> bool {
+        levels
+            .iter()
+            .all(|(skill, level)| self.level(skill) >= *level)
+    }
+
+    // the first way that works out, None if the recipe is out of reach
+    fn known_by(&self, recipe: &Recipe) -> Option<KnownBy> {
+        let own_level = |level: i32| vec![(recipe.skill_used.clone(), level)];
+        let autolearn = match &recipe.autolearn {
+            schema::Autolearn::No => None,
+            schema::Autolearn::Yes => Some(own_level(recipe.difficulty)),
+            schema::Autolearn::Complex(levels) => Some(levels.clone()),
+        };
+        if autolearn.is_some_and(|levels| self.has_levels(&levels)) {
+            return Some(KnownBy::Autolearn);
+        }
+        let from_book = recipe.book_learn.iter().find(|(book, level)| {
+            self.books.contains(book) && self.level(&recipe.skill_used) >= *level
+        });
+        if let Some((book, _)) = from_book {
+            return Some(KnownBy::Book(book.clone()));
+        }
+        let decomp = match &recipe.decomp_learn {
+            None => None,
+            Some(schema::DecompLearn::Level(level)) => Some(own_level(*level)),
+            Some(schema::DecompLearn::Skills(levels)) => Some(levels.clone()),
+        };
+        let disassembled = self.disassembled.contains(&recipe.result);
+        if disassembled && decomp.is_some_and(|levels| self.has_levels(&levels)) {
+            return Some(KnownBy::Disassembly);
+        }
+        None
+    }
 }
 
 fn find_everything_craftable_from(
     from: &[String],
     recipes_db: &[Recipe],
-    requirements_db: &[Requirement],
     blacklist: &[String],
     unobtainables: &[String],
+    tools: Option<&OwnedTools>,
+    knowledge: Option<&Knowledge>,
 ) -> CraftableGraph {
     let mut nodes: HashSet<Node> = from
         .iter()
@@ -159,29 +366,12 @@ fn find_everything_craftable_from(
 
     let blacklist_items: Vec<_> = blacklist
         .iter()
-        .map(|x| {
-            vec![
-                Node::Item(x.to_string()),
-                Node::RequirementItem(x.to_string()),
-            ]
-        })
-        .flatten()
+        .map(|x| Node::Item(x.to_string()))
         .collect();
-    let mut unobtainables_items: Vec<_> = unobtainables
+    let unobtainables_items: Vec<_> = unobtainables
         .iter()
-        .map(|x| {
-            vec![
-                Node::Item(x.to_string()),
-                Node::RequirementItem(x.to_string()),
-            ]
-        })
-        .flatten()
+        .map(|x| Node::Item(x.to_string()))
         .collect();
-    for req in requirements_db.iter() {
-        if has_mandatory_ingredient(&unobtainables_items, &req.components) {
-            unobtainables_items.push(Node::RequirementItem(req.id.to_string()));
-        }
-    }
     // let unobtainables_items = vec![];
 
     let blacklist_qualities = &[schema::Quality {
@@ -206,7 +396,6 @@ fn find_everything_craftable_from(
         // // hack
         // if match &my_node {
         //     Node::Item(s) => s,
-        //     Node::RequirementItem(s) => s,
         // }
         // .contains("mutagen")
         // {
@@ -219,59 +408,32 @@ fn find_everything_craftable_from(
 
             // });
             for alternative in component {
-                match alternative {
-                    ComponentDesc::Plain(s, _) => {
-                        let source_node = Node::Item(s.to_string());
-                        if nodes.contains(&source_node) {
-                            let edge_added = edges.insert(Edge {
-                                source: source_node.clone(),
-                                dest: my_node.clone(),
-                                recipe_suffix: my_recipe_suffix.clone(),
-                            });
-                            if edge_added {
-                                // println!("adding {:?} -> {:?}", source_node, my_node);
-                                nodes.insert(my_node);
-                                return true;
-                            }
-                        }
-                    }
-                    ComponentDesc::List(s, _) => {
-                        let source_node = Node::RequirementItem(s.to_string());
-                        if nodes.contains(&source_node) {
-                            let edge_added = edges.insert(Edge {
-                                source: source_node.clone(),
-                                dest: my_node.clone(),
-                                recipe_suffix: my_recipe_suffix.clone(),
-                            });
-                            if edge_added {
-                                // println!("adding {:?} -> {:?}", source_node, my_node);
-                                nodes.insert(my_node);
-
-                                return true;
-                            }
-                        }
+                let source_node = component_node(alternative);
+                if nodes.contains(&source_node) {
+                    let edge_added = edges.insert(Edge {
+                        source: source_node.clone(),
+                        dest: my_node.clone(),
+                        recipe_suffix: my_recipe_suffix.clone(),
+                    });
+                    if edge_added {
+                        // println!("adding {:?} -> {:?}", source_node, my_node);
+                        nodes.insert(my_node);
+                        return true;
                     }
                 }
             }
         }
-        return false;
+        false
     }
     while any_changes {
         any_changes = false;
-        for requirement in requirements_db.iter() {
-            let my_node = Node::RequirementItem(requirement.id.to_string());
-            let my_recipe_suffix = &None;
-            any_changes |= do_stuff(
-                requirement.components.iter(),
-                &mut nodes,
-                &mut edges,
-                my_node,
-                my_recipe_suffix,
-                &blacklist_items,
-            );
-        }
-
         let is_craftable = |recipe: &Recipe| -> bool {
+            if knowledge.is_some_and(|knowledge| !knowledge.can_craft(recipe)) {
+                return false;
+            }
+            if let Some(tools) = tools {
+                return tools.can_craft(recipe);
+            }
             for quality in &recipe.qualities {
                 for b_quality in blacklist_qualities.iter() {
                     if quality.id == b_quality.id && quality.level >= b_quality.level {
@@ -289,6 +451,10 @@ fn find_everything_craftable_from(
             if has_mandatory_ingredient(&unobtainables_items, &recipe.components) {
                 continue;
             }
+            // requirement ids leave out whatever recipes they went into
+            if uses_requirement(recipe, unobtainables) || uses_requirement(recipe, blacklist) {
+                continue;
+            }
             let my_node = Node::Item(recipe.result.to_string());
             let my_recipe_suffix = &recipe.id_suffix;
 
@@ -305,29 +471,27 @@ fn find_everything_craftable_from(
     CraftableGraph { nodes, edges }
 }
 
-fn expand_pantry(from: &[Node], db: &Db, blacklist: &[String]) -> CraftableGraph {
+fn expand_pantry(
+    from: &[Node],
+    db: &Db,
+    blacklist: &[String],
+    tools: Option<&OwnedTools>,
+    knowledge: Option<&Knowledge>,
+) -> CraftableGraph {
     let mut any_new = true;
-    let mut nodes: HashSet<Node> = from.into_iter().cloned().collect();
+    let mut nodes: HashSet<Node> = from.iter().cloned().collect();
     let mut edges: HashSet<Edge> = Default::default();
 
     while any_new {
         any_new = false;
-        for req in &db.requirements {
-            if blacklist.contains(&req.id) {
+        for rec in &db.recipes {
+            if blacklist.contains(&rec.result) || uses_requirement(rec, blacklist) {
                 continue;
             }
-            if let Some(usables) = has_enough_components(&nodes, &req.components) {
-                let usables: Vec<Node> = usables.into_iter().cloned().collect();
-                let node = Node::from_requirement(req);
-                any_new |= nodes.insert(node);
-                for usable in usables {
-                    let edge = Edge::from_requirement(&usable, req);
-                    any_new |= edges.insert(edge);
-                }
-            };
-        }
-        for rec in &db.recipes {
-            if blacklist.contains(&rec.result) {
+            if tools.is_some_and(|tools| !tools.can_craft(rec)) {
+                continue;
+            }
+            if knowledge.is_some_and(|knowledge| !knowledge.can_craft(rec)) {
                 continue;
             }
             if let Some(usables) = has_enough_components(&nodes, &rec.components) {
@@ -341,11 +505,10 @@ fn expand_pantry(from: &[Node], db: &Db, blacklist: &[String]) -> CraftableGraph
             };
         }
     }
-    let graph = CraftableGraph {
+    CraftableGraph {
         nodes: nodes.into_iter().collect(),
         edges: edges.into_iter().collect(),
-    };
-    graph
+    }
 }
 
 fn extract_relevant_raw_db_items<'a, It: Iterator<Item = &'a Node>>(
@@ -355,15 +518,10 @@ fn extract_relevant_raw_db_items<'a, It: Iterator<Item = &'a Node>>(
     let mut raw_db_small = HashMap::<Node, &serde_json::Value>::new();
 
     for node in nodes_it {
-        match node {
-            Node::Item(node_name) => {
-                let item = db
-                    .lookup_item(node_name.as_str())
-                    .unwrap_or_else(|| panic!("{:?} not found", node_name));
-                raw_db_small.insert(node.clone(), item);
-            }
-            _ => {}
-        }
+        let item = db
+            .lookup_item(node.name())
+            .unwrap_or_else(|| panic!("{:?} not found", node));
+        raw_db_small.insert(node.clone(), item);
     }
     raw_db_small
 }
@@ -418,27 +576,23 @@ fn prune_irrelevant_nodes(
         any_removed = false;
         let mut irrelevant_nodes = vec![];
         for node in graph.nodes.iter() {
-            if matches!(node, Node::Item(..)) {
-                let item = raw_db_small[node];
-                if let Some(health_min) = healthy_min {
-                    let healthy_here = item
-                        .as_object()
-                        .unwrap()
-                        .get("healthy")
-                        .map(|x| x.as_i64().unwrap())
-                        .unwrap_or(0);
-                    if healthy_here < health_min as i64 {
-                        irrelevant_nodes.push(node.clone());
-                    }
+            let item = raw_db_small[node];
+            if let Some(health_min) = healthy_min {
+                let healthy_here = item
+                    .as_object()
+                    .unwrap()
+                    .get("healthy")
+                    .map(|x| x.as_i64().unwrap())
+                    .unwrap_or(0);
+                if healthy_here < health_min as i64 {
+                    irrelevant_nodes.push(node.clone());
                 }
-                if let Some(restrict) = restrict_types {
-                    let type_here = item["type"].as_str().unwrap().to_string();
-                    if !restrict.contains(&type_here) {
-                        irrelevant_nodes.push(node.clone());
-                    }
+            }
+            if let Some(restrict) = restrict_types {
+                let type_here = item["type"].as_str().unwrap().to_string();
+                if !restrict.contains(&type_here) {
+                    irrelevant_nodes.push(node.clone());
                 }
-            } else {
-                irrelevant_nodes.push(node.clone());
             }
         }
         irrelevant_nodes.sort();
@@ -471,7 +625,7 @@ fn parse_spoils_in_bad(time_v: &serde_json::Value) -> String {
 
 fn get_item_name(item: &serde_json::Value) -> String {
     let name: schema::Name = serde_json::from_value(item["name"].clone()).unwrap();
-    name.to_string()
+    name.translated().to_string()
 }
 
 fn get_ingredient_multiplicity(
@@ -480,66 +634,57 @@ fn get_ingredient_multiplicity(
 ) -> Option<i32> {
     for component in components {
         for alternative in component {
-            if alternative.name() == ingredient {
-                return Some(alternative.amount());
+            if alternative.id == ingredient {
+                return Some(alternative.amount);
             }
         }
     }
     None
 }
 
-fn make_graphviz_one(db: &Db, out_path: impl AsRef<std::path::Path>, graph: &CraftableGraph) {
+fn make_graphviz_one(
+    db: &Db,
+    out_path: impl AsRef<std::path::Path>,
+    graph: &CraftableGraph,
+    dot: &Path,
+) {
     use itertools::Itertools;
     use std::io::Write;
     let out_path = out_path.as_ref();
-    let out_dir = out_path.parent().unwrap();
-    std::fs::create_dir_all(out_dir).unwrap();
-    let out_file = &mut std::fs::OpenOptions::new()
-        .truncate(true)
-        .write(true)
-        .create(true)
-        .open(out_path)
-        .unwrap();
+    let out_file = &mut crate::create_output(out_path);
 
-    write!(out_file, "digraph {{\n").unwrap();
+    writeln!(out_file, "digraph {{").unwrap();
 
-    let node_to_id = |node: &Node| match node {
-        Node::Item(i) => i.to_string(),
-        Node::RequirementItem(i) => format!("*{}", i),
-    };
+    let node_to_id = |node: &Node| node.name().to_string();
     for node in graph.nodes.iter().sorted() {
         write!(out_file, "{:?} ", node_to_id(node)).unwrap();
-        match node {
-            Node::Item(i) => {
-                let other_item = db.lookup_item(i).unwrap();
-
-                // println!("{}", other_item.to_string());
-
-                // println!("{}", i);
-                let mut label_stuff = vec![node_to_id(node)];
-                let mut other_attrs = vec![];
-                let name = get_item_name(other_item);
-                if &name != i {
-                    label_stuff.push(name);
-                }
-                if let Some(spoil) = other_item.get("spoils_in") {
-                    label_stuff.push(parse_spoils_in_bad(spoil));
-                }
-                if let Some(healthy) = other_item.get("healthy") {
-                    label_stuff.push(format!("healty: {}", healthy));
-                    if healthy.as_i64().unwrap() > 0 {
-                        other_attrs.push("color=blue shape=rectangle");
-                    }
-                }
+        let i = node.name();
+        let other_item = db.lookup_item(i).unwrap();
 
-                write!(out_file, r"[").unwrap();
-                write!(out_file, "label=\"{}\" ", label_stuff.join(r"\n")).unwrap();
-                write!(out_file, "{}", other_attrs.join(" ")).unwrap();
-                write!(out_file, r"]").unwrap();
+        // println!("{}", other_item.to_string());
+
+        // println!("{}", i);
+        let mut label_stuff = vec![node_to_id(node)];
+        let mut other_attrs = vec![];
+        let name = get_item_name(other_item);
+        if &name != i {
+            label_stuff.push(name);
+        }
+        if let Some(spoil) = other_item.get("spoils_in") {
+            label_stuff.push(parse_spoils_in_bad(spoil));
+        }
+        if let Some(healthy) = other_item.get("healthy") {
+            label_stuff.push(format!("healty: {}", healthy));
+            if healthy.as_i64().unwrap() > 0 {
+                other_attrs.push("color=blue shape=rectangle");
             }
-            _ => {}
-        };
-        write!(out_file, ";\n").unwrap();
+        }
+
+        write!(out_file, r"[").unwrap();
+        write!(out_file, "label=\"{}\" ", label_stuff.join(r"\n")).unwrap();
+        write!(out_file, "{}", other_attrs.join(" ")).unwrap();
+        write!(out_file, r"]").unwrap();
+        writeln!(out_file, ";").unwrap();
     }
 
     // collect into a vec first to deduplicate identical edges
@@ -555,23 +700,8 @@ fn make_graphviz_one(db: &Db, out_path: impl AsRef<std::path::Path>, graph: &Cra
         .unwrap();
         let mut attributes = vec![];
         match &edge.dest {
-            Node::RequirementItem(id) => {
-                attributes.push(r#" color="red" "#.to_string());
-                let req = db.lookup_requirement(&id);
-                let from_count =
-                    get_ingredient_multiplicity(&req.components, edge.source.name()).expect(
-                        &format!("source not found?? for {} in {:?}", edge.source.name(), id),
-                    );
-                if from_count != 1 {
-                    attributes.push(format!(
-                        r#"label="{}:1 x{:0.2}""#,
-                        from_count,
-                        1.0 / from_count as f32
-                    ));
-                }
-            }
             Node::Item(id) => {
-                let rec = db.lookup_recipe(&id, edge.recipe_suffix.as_deref());
+                let rec = db.lookup_recipe(id, edge.recipe_suffix.as_deref());
                 let result = db.lookup_item(&rec.result).unwrap();
                 let from_count = get_ingredient_multiplicity(&rec.components, edge.source.name())
                     .expect("source not found??");
@@ -592,27 +722,39 @@ fn make_graphviz_one(db: &Db, out_path: impl AsRef<std::path::Path>, graph: &Cra
             }
         };
         write!(edge_serialized, "[{}]", attributes.join(" ")).unwrap();
-        write!(edge_serialized, ";\n").unwrap();
+        writeln!(edge_serialized, ";").unwrap();
         edges_serialized.push(edge_serialized);
     }
     edges_serialized.sort();
     edges_serialized.dedup();
     for edge in edges_serialized {
-        out_file.write(&edge).unwrap();
+        out_file.write_all(&edge).unwrap();
     }
 
     write!(out_file, "}}").unwrap();
     out_file.sync_all().unwrap();
-    drop(out_file);
     println!("invoking dot");
-    std::process::Command::new(r#"C:\soft\graphviz\bin\dot.exe"#)
+    let rendered = std::process::Command::new(dot)
         .arg(out_path)
         .arg("-Tsvg")
         .arg("-O")
-        .spawn()
-        .unwrap()
-        .wait()
-        .unwrap();
+        .status();
+    // the .gv is still there to render by hand
+    match rendered {
+        Ok(status) if status.success() => {}
+        Ok(status) => eprintln!(
+            "error: {} failed on {}: {}",
+            dot.display(),
+            out_path.display(),
+            status
+        ),
+        Err(e) => eprintln!(
+            "error: can't run {} to render {}: {}",
+            dot.display(),
+            out_path.display(),
+            e
+        ),
+    }
 }
 
 #[derive(Debug, serde::Deserialize)]
@@ -632,32 +774,78 @@ struct Input {
     #[serde(default)]
     unobtainables: Vec<String>,
     #[serde(default)]
+    #[allow(dead_code)]
     show_multiplier: bool,
     restrict_type: Option<Vec<String>>,
+    // tools owned, by name or id, optionally with how many charges they have: ["soldering iron", 50].
+    // Without it recipes aren't checked for tools at all
+    tools: Option<Vec<OwnedTool>>,
+    // skill levels, for leaving out recipes the character wouldn't know.
+    // Without it every recipe counts as known
+    skills: Option<HashMap<String, i32>>,
+    // books owned, by name or id, teaching recipes on top of what the skills give
+    #[serde(default)]
+    books: Vec<String>,
+    // items taken apart already, by name or id, teaching their own recipe if the skills allow
+    #[serde(default)]
+    disassembled: Vec<String>,
+    // proficiency ids known; recipes requiring others are left out
+    #[serde(default)]
+    proficiencies: Vec<String>,
+    // also leave out recipes only slowed down by missing proficiencies
+    #[serde(default)]
+    strict_proficiencies: bool,
 }
 
-#[allow(dead_code)]
-pub(crate) fn graphviz_all_inputs(db: &Db) {
-    for entry in std::fs::read_dir("input/graphs").unwrap() {
-        let entry = entry.unwrap();
-        let filename: PathBuf = entry.path().into();
-        if filename.extension().map(|e| e.to_str()) != Some(Some("json5")) {
-            continue;
+pub(crate) fn graphviz_all_inputs(db: &Db, input_path: &Path, out_dir: &Path, dot: &Path) {
+    let input_files: Vec<PathBuf> = if input_path.is_dir() {
+        let mut files = vec![];
+        for entry in std::fs::read_dir(input_path).unwrap() {
+            let filename = entry.unwrap().path();
+            if filename.extension().map(|e| e.to_str()) != Some(Some("json5")) {
+                continue;
+            }
+            files.push(filename);
         }
-        // let f = std::fs::File::open(filename).unwrap();
-        let input: Input = json5::from_str(&std::fs::read_to_string(&filename).unwrap()).unwrap();
+        files.sort();
+        files
+    } else {
+        vec![input_path.to_owned()]
+    };
+    for filename in input_files {
+        let input: Input = crate::read_input(&filename);
+        let tools = input
+            .tools
+            .as_ref()
+            .map(|owned| OwnedTools::new(db, &filename, owned));
+        let knowledge = input.skills.as_ref().map(|skills| {
+            Knowledge::new(
+                db,
+                &filename,
+                skills,
+                &input.books,
+                &input.disassembled,
+                &input.proficiencies,
+                input.strict_proficiencies,
+            )
+        });
+        let blacklist = to_filter_list(&input.blacklist, db, &filename, "blacklist");
+        let unobtainables = to_filter_list(&input.unobtainables, db, &filename, "unobtainables");
+        let hide = crate::resolve_input(db, &filename, "hide", &input.hide);
         let mut out;
         if !input.pantry.is_empty() {
-            let start = to_node_list(&input.pantry, db);
-            out = expand_pantry(&start, db, &input.blacklist);
+            let start = to_node_list(&input.pantry, db, &filename, "pantry");
+            out = expand_pantry(&start, db, &blacklist, tools.as_ref(), knowledge.as_ref());
         // out = break_cycles(out, &start);
         } else {
+            let have = crate::resolve_input(db, &filename, "have", &input.have);
             out = find_everything_craftable_from(
-                &input.have,
+                &have,
                 &db.recipes,
-                &db.requirements,
-                &input.blacklist,
-                &input.unobtainables,
+                &blacklist,
+                &unobtainables,
+                tools.as_ref(),
+                knowledge.as_ref(),
             );
         }
         println!("nodes: {}", out.nodes.len());
@@ -670,12 +858,12 @@ pub(crate) fn graphviz_all_inputs(db: &Db) {
             db,
             input.healthy_min,
             input.restrict_type.as_deref(),
-            &input.hide,
+            &hide,
         );
 
-        let out_path = std::path::Path::new("out")
-            .join(filename.file_name().unwrap().to_owned())
+        let out_path = out_dir
+            .join(filename.file_name().unwrap())
             .with_extension("gv");
-        make_graphviz_one(&db, out_path, &out);
+        make_graphviz_one(db, out_path, &out, dot);
     }
 }
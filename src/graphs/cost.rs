@@ -0,0 +1,182 @@
+// What crafting something takes out of the stockpile, down to base materials: components
+// and tool charges (requirements are already inlined into both), with intermediate items that
+// can't be found anyway crafted from their own recipe. Alternatives are picked from what's left
+// in the inventory when one is given, otherwise the first one that isn't forbidden is taken.
+use super::{Knowledge, Node};
+use crate::db::Db;
+use crate::schema::{ComponentDesc, Recipe};
+use std::collections::{BTreeMap, HashMap};
+
+// item id -> count, or charges for things used up by charges, like the game counts them
+pub(super) type Materials = BTreeMap<String, i64>;
+
+pub(super) struct Costing<'a> {
+    db: &'a Db,
+    // what's left of it, None if there's no inventory to go by
+    inventory: Option<HashMap<String, i64>>,
+    // never picked, nor crafted through
+    forbidden: &'a [Node],
+    // requirement ids, recipes using them aren't crafted through
+    forbidden_requirements: &'a [String],
+}
+
+impl<'a> Costing<'a> {
+    pub(super) fn new(
+        db: &'a Db,
+        inventory: Option<HashMap<String, i64>>,
+        forbidden: &'a [Node],
+        forbidden_requirements: &'a [String],
+    ) -> Costing<'a> {
+        Costing {
+            db,
+            inventory,
+            forbidden,
+            forbidden_requirements,
+        }
+    }
+
+    fn left(&self, id: &str) -> Option<i64> {
+        let inventory = self.inventory.as_ref()?;
+        Some(inventory.get(id).copied().unwrap_or(0))
+    }
+
+    // what's left of `id` once `used` is taken out, None without an inventory
+    fn available(&self, id: &str, used: &Materials) -> Option<i64> {
+        Some(self.left(id)? - used.get(id).copied().unwrap_or(0))
+    }
+
+    // The materials `times` crafts of `recipe` use up. Intermediates are only crafted
+    // from recipes the character knows.
+    pub(super) fn cost(&self, knowledge: &Knowledge, recipe: &Recipe, times: i64) -> Materials {
+        let mut materials = Materials::new();
+        let mut stack = vec![recipe.result.clone()];
+        self.add_cost(knowledge, recipe, times, &mut materials, &mut stack);
+        materials
+    }
+
+    // `stack` is the items being costed right now, so that nothing is made from itself
+    fn add_cost(
+        &self,
+        knowledge: &Knowledge,
+        recipe: &Recipe,
+        times: i64,
+        materials: &mut Materials,
+        stack: &mut Vec<String>,
+    ) {
+        for group in &recipe.components {
+            let (desc, amount) = match self.pick(group, times, |c| c.amount as i64, materials) {
+                Some(picked) => picked,
+                None => continue,
+            };
+            let in_stock = self.available(&desc.id, materials).unwrap_or(0).max(0);
+            match self.intermediate(knowledge, &desc.id, stack) {
+                // what's in stock first, the rest crafted
+                Some(made_from) if in_stock < amount => {
+                    if in_stock > 0 {
+                        *materials.entry(desc.id.clone()).or_default() += in_stock;
+                    }
+                    let made = self.yield_of(made_from);
+                    let crafts = (amount - in_stock + made - 1) / made;
+                    stack.push(desc.id.clone());
+                    self.add_cost(knowledge, made_from, crafts, materials, stack);
+                    stack.pop();
+                }
+                _ => *materials.entry(desc.id.clone()).or_default() += amount,
+            }
+        }
+        for group in &recipe.tools {
+            // tools used up by charges; those that aren't cost nothing
+            if group.iter().all(|tool| tool.charges().is_none()) {
+                continue;
+            }
+            let charges = |tool: &ComponentDesc| tool.charges().unwrap_or(0) as i64;
+            if let Some((tool, amount)) = self.pick(group, times, charges, materials) {
+                *materials.entry(tool.id.clone()).or_default() += amount;
+            }
+        }
+    }
+
+    // the first allowed alternative there's still enough of, or just the first allowed one,
+    // None if there's none
+    fn pick<'g>(
+        &self,
+        group: &'g [ComponentDesc],
+        times: i64,
+        amount: impl Fn(&ComponentDesc) -> i64,
+        used: &Materials,
+    ) -> Option<(&'g ComponentDesc, i64)> {
+        let allowed = || {
+            group
+                .iter()
+                .filter(|desc| !self.forbidden.contains(&super::component_node(desc)))
+        };
+        let enough = allowed().find(|desc| {
+            let available = self.available(&desc.id, used);
+            available.is_some_and(|available| available >= amount(desc) * times)
+        });
+        let desc = enough.or_else(|| allowed().next())?;
+        Some((desc, amount(desc) * times))
+    }
+
+    // a known recipe for `id`, if it's something only crafting gets
+    fn intermediate(
+        &self,
+        knowledge: &Knowledge,
+        id: &str,
+        stack: &[String],
+    ) -> Option<&'a Recipe> {
+        if stack.iter().any(|s| s == id) || self.db.itemgroups_with(id).next().is_some() {
+            return None;
+        }
+        let mut recipes = self.db.recipes_for(id);
+        recipes.find(|recipe| {
+            knowledge.can_craft(recipe)
+                && !super::has_mandatory_ingredient(self.forbidden, &recipe.components)
+                && !super::uses_requirement(recipe, self.forbidden_requirements)
+        })
+    }
+
+    // how many of the result one craft makes
+    fn yield_of(&self, recipe: &Recipe) -> i64 {
+        let result = self.db.lookup_item(&recipe.result);
+        let charges = recipe
+            .charges
+            .map(|c| c as i64)
+            .or_else(|| result.and_then(|item| item["charges"].as_i64()))
+            .unwrap_or(1);
+        (charges * recipe.result_mult.unwrap_or(1) as i64).max(1)
+    }
+
+    // takes `materials` out of the inventory, as far as there's any
+    pub(super) fn take(&mut self, materials: &Materials) {
+        if let Some(inventory) = &mut self.inventory {
+            for (id, amount) in materials {
+                let left = inventory.entry(id.clone()).or_default();
+                *left = (*left - amount).max(0);
+            }
+        }
+    }
+
+    // what there isn't enough of for `materials`, without taking anything
+    pub(super) fn shortfall(&self, materials: &Materials) -> Materials {
+        let mut short = Materials::new();
+        for (id, amount) in materials {
+            match self.left(id) {
+                Some(left) if left < *amount => {
+                    short.insert(id.clone(), amount - left);
+                }
+                _ => {}
+            }
+        }
+        short
+    }
+}
+
+// "bone x16, rag x4"
+pub(super) fn describe(materials: &Materials) -> String {
+    let parts: Vec<String> = materials
+        .iter()
+        .map(|(id, amount)| format!("{} x{}", id, amount))
+        .collect();
+    parts.join(", ")
+}
@@ -0,0 +1,146 @@
+// Which known recipes teach each proficiency fastest. Crafting without a proficiency practices it
+// for as long as the crafting takes (slowed down by lacking it), times the recipe's
+// learning_time_multiplier, and no further than the recipe's max_experience.
+use crate::db::Db;
+use std::path::Path;
+
+type Map<K, V> = std::collections::HashMap<K, V>;
+
+fn default_top() -> usize {
+    5
+}
+
+#[derive(serde::Deserialize, Debug)]
+#[serde(deny_unknown_fields)]
+struct ProficienciesInput {
+    skills: Map<String, i32>,
+    // owned, by name or id
+    #[serde(default)]
+    books: Vec<String>,
+    // taken apart already, by name or id
+    #[serde(default)]
+    disassembled: Vec<String>,
+    // known already
+    #[serde(default)]
+    proficiencies: Vec<String>,
+    // only these, every one not known yet if empty
+    #[serde(default)]
+    want: Vec<String>,
+    // recipes shown per proficiency
+    #[serde(default = "default_top")]
+    top: usize,
+}
+
+pub(crate) fn proficiencies(db: &Db, input_path: &Path, out_path: &Path) {
+    let input: ProficienciesInput = crate::read_input(input_path);
+    let knowledge = super::Knowledge::new(
+        db,
+        input_path,
+        &input.skills,
+        &input.books,
+        &input.disassembled,
+        &input.proficiencies,
+        false,
+    );
+    for id in &input.want {
+        if db.lookup_proficiency(id).is_none() {
+            eprintln!(
+                "error: {}: want: no proficiency {:?}",
+                input_path.display(),
+                id
+            );
+            std::process::exit(1)
+        }
+    }
+
+    let mut wanted: Vec<&crate::schema::Proficiency> = db
+        .proficiencies
+        .iter()
+        .filter(|p| p.can_learn && !input.proficiencies.contains(&p.id))
+        .filter(|p| input.want.is_empty() || input.want.contains(&p.id))
+        .collect();
+    wanted.sort_by(|a, b| a.id.cmp(&b.id));
+
+    let mut out_lines = Vec::new();
+    for proficiency in wanted {
+        let mut header = format!(
+            "{} ({}) - {} of practice",
+            proficiency.id,
+            proficiency.name.translated(),
+            proficiency.time_to_learn
+        );
+        let missing: Vec<&str> = proficiency
+            .required_proficiencies
+            .iter()
+            .filter(|p| !input.proficiencies.contains(p))
+            .map(|p| p.as_str())
+            .collect();
+        if !missing.is_empty() {
+            header.push_str(&format!(", needs {} first", missing.join(", ")));
+        }
+        out_lines.push(header);
+
+        let mut teachers = vec![];
+        for recipe in &db.recipes {
+            let practiced = recipe
+                .proficiencies
+                .iter()
+                .find(|p| p.proficiency == proficiency.id);
+            let practiced = match practiced {
+                Some(practiced) => practiced,
+                None => continue,
+            };
+            let known_by = match knowledge.known_by(recipe) {
+                Some(known_by) => known_by,
+                None => continue,
+            };
+            let slowdown = match knowledge.time_multiplier(recipe) {
+                Some(slowdown) => slowdown,
+                None => continue,
+            };
+            let craft_time = recipe.time.scaled(slowdown);
+            let per_craft = craft_time.scaled(practiced.learning_time_multiplier as f64);
+            if per_craft.moves() <= 0 {
+                continue;
+            }
+            // practice past the cap is wasted
+            let cap = practiced.max_experience;
+            let capped = cap.filter(|cap| *cap < proficiency.time_to_learn);
+            let needed = capped.unwrap_or(proficiency.time_to_learn).moves();
+            let crafts = (needed + per_craft.moves() - 1) / per_craft.moves();
+            teachers.push((
+                capped,
+                craft_time * crafts,
+                crafts,
+                craft_time,
+                recipe,
+                known_by,
+            ));
+        }
+        // the ones that can teach all of it first
+        teachers.sort_by_key(|(capped, total, ..)| (capped.is_some(), *total));
+        if teachers.is_empty() {
+            out_lines.push("  no known recipe practices it".to_string());
+        }
+        for (capped, total, crafts, craft_time, recipe, known_by) in
+            teachers.into_iter().take(input.top)
+        {
+            let mut line = format!(
+                "  {} ({} x {}) - {:<20} - {} ({})",
+                total,
+                crafts,
+                craft_time,
+                recipe.result,
+                super::get_item_name(db.lookup_item(&recipe.result).unwrap()),
+                known_by
+            );
+            if let Some(cap) = capped {
+                line.push_str(&format!(", teaches only {}", cap));
+            }
+            out_lines.push(line);
+        }
+    }
+
+    let mut out_file = crate::create_output(out_path);
+    std::io::Write::write_all(&mut out_file, out_lines.join("\n").as_bytes()).unwrap();
+}
@@ -1,7 +1,7 @@
+use super::cost::{self, Costing, Materials};
 use crate::db::Db;
-use crate::schema;
-
-use schema::Recipe;
+use crate::schema::{Duration, Recipe};
+use std::path::Path;
 
 type Map<K, V> = std::collections::HashMap<K, V>;
 
@@ -11,44 +11,87 @@ struct TrainInput {
     forbidden_mats: Vec<String>,
     forbidden_meta: Vec<String>,
     skills: Map<String, i32>,
+    // crafting this many at once, which is faster for some recipes
+    #[serde(default = "default_batch")]
+    batch: i32,
+    // owned, by name or id; recipes they teach at the current skill count as known
+    #[serde(default)]
+    books: Vec<String>,
+    // taken apart already, by name or id; their own recipe counts as known if the skills allow
+    #[serde(default)]
+    disassembled: Vec<String>,
+    // proficiency ids known; missing ones slow crafting down, required ones rule the recipe out
+    #[serde(default)]
+    proficiencies: Vec<String>,
+    // rule out recipes missing any proficiency instead
+    #[serde(default)]
+    strict_proficiencies: bool,
+    // percent of the way to the next level already
+    #[serde(default)]
+    progress: f64,
+    // scales all practice, 100 being the usual
+    #[serde(default = "default_focus")]
+    focus: f64,
+    // plan the training up to this level
+    target_level: Option<i32>,
+    // cost this many crafts of each recipe, rather than as many as the next level takes
+    crafts: Option<i64>,
+    // what there is to craft with, by name or id: {"bone": 20}.
+    // Recipes and plans it covers come first; an empty one is the same as none
+    inventory: Option<Map<String, i64>>,
 }
 
-fn recipe_is_known(recipe: &Recipe, skills: &[(String, i32)]) -> bool {
-    let need_skills = match &recipe.autolearn {
-        schema::Autolearn::No => return false,
-        schema::Autolearn::Yes => vec![(recipe.skill_used.clone(), recipe.difficulty)],
-        schema::Autolearn::Complex(v) => v.clone(),
-    };
-    for (name, lvl) in skills {
-        for (need_name, need_lvl) in &need_skills {
-            if name != need_name {
-                continue;
-            };
-            if lvl < need_lvl {
-                return false;
-            }
-        }
-    }
-    return true;
+fn default_batch() -> i32 {
+    1
 }
 
-#[allow(dead_code)]
-pub(crate) fn train(db: &Db) {
-    let input: TrainInput =
-        json5::from_str(&std::fs::read_to_string("input/training.json5").unwrap()).unwrap();
-
-    let mut forbidden_mats = super::to_node_list(&input.forbidden_mats, db);
-    forbidden_mats.extend(
-        input
-            .forbidden_meta
-            .iter()
-            .map(|x| super::Node::RequirementItem(x.to_string())),
-    );
-    // let skills = vec![(input.want_skill.clone(), input.current_level)];
-    let skills: Vec<_> = input.skills.clone().into_iter().collect();
-    let current_level = input.skills[&input.want_skill].clone();
+fn default_focus() -> f64 {
+    100.0
+}
+
+// Crafting practices the skill used by (15 × difficulty + 10) for each one in a batch and
+// for every 5 minutes (30000 moves) the batch takes, scaled by focus,
+// until the skill gets to 1.25 × difficulty.
+// `batch_time` is what the batch really takes, missing proficiencies and all.
+fn practice_per_craft(recipe: &Recipe, batch: i32, batch_time: Duration, focus: f64) -> f64 {
+    let time = batch_time.moves() as f64 / 30000.0;
+    (15 * recipe.difficulty + 10) as f64 * (batch as f64 + time) * focus / 100.0
+}
+
+fn skill_cap(recipe: &Recipe) -> i32 {
+    (recipe.difficulty as f32 * 1.25).floor() as i32
+}
 
-    let mut trainables = vec![];
+// to get from `level` to the next one
+fn practice_to_next(level: i32) -> f64 {
+    100.0 * ((level + 1) * (level + 1)) as f64
+}
+
+struct Candidate<'a> {
+    recipe: &'a Recipe,
+    known_by: super::KnownBy,
+    // of a whole batch
+    craft_time: Duration,
+    practice: f64,
+}
+
+impl Candidate<'_> {
+    // batches crafted, and how long that takes
+    fn to_practice(&self, practice: f64) -> (i64, Duration) {
+        let crafts = (practice / self.practice).ceil() as i64;
+        (crafts, self.craft_time * crafts)
+    }
+}
+
+// what can be crafted for practice at `level` of the wanted skill
+fn candidates<'a>(
+    db: &'a Db,
+    input: &TrainInput,
+    knowledge: &super::Knowledge,
+    forbidden_mats: &[super::Node],
+    level: i32,
+) -> Vec<Candidate<'a>> {
+    let mut candidates = vec![];
     for recipe in &db.recipes {
         if recipe.result.starts_with("seed_") {
             continue;
@@ -59,48 +102,271 @@ pub(crate) fn train(db: &Db) {
         if recipe.skill_used != input.want_skill {
             continue;
         }
-        if !recipe_is_known(recipe, &skills) {
+        let known_by = match knowledge.known_by(recipe) {
+            Some(known_by) => known_by,
+            None => continue,
+        };
+        let slowdown = match knowledge.time_multiplier(recipe) {
+            Some(slowdown) => slowdown,
+            None => continue,
+        };
+        if skill_cap(recipe) <= level {
+            continue;
+        }
+        if super::has_mandatory_ingredient(forbidden_mats, &recipe.components) {
             continue;
         }
-        let recipe_cap = (recipe.difficulty as f32 * 1.25).floor() as i32;
-        if recipe_cap < current_level {
+        if super::uses_requirement(recipe, &input.forbidden_meta) {
             continue;
         }
-        if super::has_mandatory_ingredient(&forbidden_mats, &recipe.components) {
+        let craft_time = recipe.batch_time(input.batch).scaled(slowdown);
+        let practice = practice_per_craft(recipe, input.batch, craft_time, input.focus);
+        if practice <= 0.0 {
             continue;
         }
-        trainables.push(recipe);
+        candidates.push(Candidate {
+            recipe,
+            known_by,
+            craft_time,
+            practice,
+        });
+    }
+    candidates
+}
+
+pub(crate) fn train(db: &Db, input_path: &Path, out_path: &Path) {
+    let input: TrainInput = crate::read_input(input_path);
+
+    let forbidden_mats =
+        super::to_node_list(&input.forbidden_mats, db, input_path, "forbidden_mats");
+    for id in &input.forbidden_meta {
+        if db.find_requirement(id).is_none() {
+            eprintln!(
+                "error: {}: forbidden_meta: no requirement {:?}",
+                input_path.display(),
+                id
+            );
+            std::process::exit(1)
+        }
     }
+    let mut knowledge = super::Knowledge::new(
+        db,
+        input_path,
+        &input.skills,
+        &input.books,
+        &input.disassembled,
+        &input.proficiencies,
+        input.strict_proficiencies,
+    );
+    // not having the skill at all is level 0
+    let current_level = knowledge.level(&input.want_skill);
+    let left = practice_to_next(current_level) * (1.0 - input.progress / 100.0);
+
+    let inventory = input
+        .inventory
+        .as_ref()
+        .filter(|inventory| !inventory.is_empty());
+    let inventory = inventory.map(|inventory| {
+        let names: Vec<String> = inventory.keys().cloned().collect();
+        let ids = crate::resolve_input(db, input_path, "inventory", &names);
+        let mut resolved = Map::new();
+        for (id, name) in ids.into_iter().zip(&names) {
+            *resolved.entry(id).or_default() += inventory[name];
+        }
+        resolved
+    });
+    let mut costing = Costing::new(db, inventory, &forbidden_mats, &input.forbidden_meta);
+    // what `batches` crafts use up, and what the inventory is short of for them
+    let cost_of = |costing: &Costing, knowledge: &super::Knowledge, recipe, batches: i64| {
+        let materials = costing.cost(knowledge, recipe, batches * input.batch as i64);
+        let short = costing.shortfall(&materials);
+        (materials, short)
+    };
+    let cost_line = |materials: &Materials, short: &Materials| {
+        let mut line = format!("      $ {}", cost::describe(materials));
+        if !short.is_empty() {
+            line.push_str(&format!(", short of {}", cost::describe(short)));
+        }
+        line
+    };
 
-    trainables.sort_by_key(|x| x.time.to_seconds());
+    let mut trainables: Vec<_> = candidates(db, &input, &knowledge, &forbidden_mats, current_level)
+        .into_iter()
+        .map(|candidate| {
+            let (crafts, time) = candidate.to_practice(left);
+            let costed = input.crafts.unwrap_or(crafts);
+            let (materials, short) = cost_of(&costing, &knowledge, candidate.recipe, costed);
+            (candidate, crafts, time, materials, short)
+        })
+        .collect();
+    trainables.sort_by_key(|(candidate, _, time, _, short)| {
+        (!short.is_empty(), *time, candidate.craft_time)
+    });
     let mut out_lines = Vec::new();
-    for recipe in trainables {
+    for (candidate, crafts, time, materials, short) in &trainables {
+        let recipe = candidate.recipe;
         out_lines.push(format!(
-            "{} ({}) {} - {:<20} - {}",
-            recipe.time.to_seconds(),
-            recipe.time.to_human(),
+            "{} to level {} ({} x {}, {} practice each) {} - {:<20} - {} ({})",
+            time,
+            current_level + 1,
+            crafts,
+            candidate.craft_time,
+            candidate.practice,
             if recipe.reversible { "*" } else { " " },
             recipe.result,
-            super::get_item_name(db.lookup_item(&recipe.result).unwrap())
+            super::get_item_name(db.lookup_item(&recipe.result).unwrap()),
+            candidate.known_by
         ));
         for (skill, level) in &recipe.skills_required {
             out_lines.push(format!("      = {}+ {}", level, skill));
         }
+        for missing in knowledge.missing_proficiencies(recipe) {
+            out_lines.push(format!("      ! {}", missing));
+        }
+        out_lines.push(cost_line(materials, short));
         for r in &recipe.components {
             let words = r
                 .iter()
-                .map(|x| format!("{} x{}", x.name(), x.amount()))
+                .map(|x| {
+                    // what disassembling it won't give back
+                    let lost = if recipe.reversible && !x.recoverable {
+                        " (not recovered)"
+                    } else {
+                        ""
+                    };
+                    format!("{} x{}{}", x.id, x.amount, lost)
+                })
                 .collect::<Vec<_>>()
                 .join(", ");
             out_lines.push(format!("  - {}", words));
         }
     }
 
-    let mut out_file = std::fs::OpenOptions::new()
-        .create(true)
-        .truncate(true)
-        .write(true)
-        .open("out/train.txt")
-        .unwrap();
+    // the fastest recipe at each level, as the skill goes up and more recipes are learnt
+    if let Some(target_level) = input.target_level {
+        out_lines.push(String::new());
+        out_lines.push(format!("plan from {} to {}:", current_level, target_level));
+        let mut total = Duration::default();
+        let mut used = Materials::new();
+        let mut reached = current_level;
+        for level in current_level..target_level {
+            knowledge.skills.insert(input.want_skill.clone(), level);
+            let needed = if level == current_level {
+                left
+            } else {
+                practice_to_next(level)
+            };
+            let options = candidates(db, &input, &knowledge, &forbidden_mats, level);
+            // what the inventory left covers first
+            let costed = options.into_iter().map(|candidate| {
+                let (crafts, time) = candidate.to_practice(needed);
+                let (materials, short) = cost_of(&costing, &knowledge, candidate.recipe, crafts);
+                (candidate, crafts, time, materials, short)
+            });
+            let best = costed.min_by_key(|(candidate, _, time, _, short)| {
+                (!short.is_empty(), *time, candidate.craft_time)
+            });
+            let (best, crafts, time, materials, short) = match best {
+                Some(best) => best,
+                None => {
+                    out_lines.push(format!(
+                        "  {} -> {}: nothing to practice on",
+                        level,
+                        level + 1
+                    ));
+                    break;
+                }
+            };
+            costing.take(&materials);
+            for (id, amount) in &materials {
+                *used.entry(id.clone()).or_default() += amount;
+            }
+            total += time;
+            reached = level + 1;
+            out_lines.push(format!(
+                "  {} -> {}: {} - {} x {}, {} each",
+                level,
+                level + 1,
+                time,
+                crafts,
+                best.recipe.result,
+                best.craft_time
+            ));
+            out_lines.push(cost_line(&materials, &short));
+        }
+        out_lines.push(format!("  total: {} to level {}", total, reached));
+        out_lines.push(format!("  using up {}", cost::describe(&used)));
+    }
+
+    let mut out_file = crate::create_output(out_path);
     std::io::Write::write_all(&mut out_file, out_lines.join("\n").as_bytes()).unwrap();
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn practice() {
+        let recipe = |time: &str| -> Recipe {
+            serde_json::from_value(serde_json::json!({
+                "result": "spear_bone",
+                "difficulty": 3,
+                "time": time,
+            }))
+            .unwrap()
+        };
+        let short = recipe("5 m");
+        let long = recipe("1 h");
+        assert_eq!(skill_cap(&short), 3);
+        assert_eq!(practice_to_next(2), 900.0);
+        // 55 for each of the two and for each 5 minutes of the 10 they take, at half focus
+        assert_eq!(
+            practice_per_craft(&short, 2, short.batch_time(2), 50.0),
+            110.0
+        );
+        // the time makes up most of a long one
+        assert_eq!(
+            practice_per_craft(&long, 1, long.batch_time(1), 100.0),
+            715.0
+        );
+
+        let candidate = Candidate {
+            recipe: &short,
+            known_by: super::super::KnownBy::Autolearn,
+            craft_time: short.batch_time(2),
+            practice: practice_per_craft(&short, 2, short.batch_time(2), 100.0),
+        };
+        let (crafts, time) = candidate.to_practice(practice_to_next(1));
+        assert_eq!(crafts, 2);
+        assert_eq!(time, "20 m".parse().unwrap());
+    }
+
+    #[test]
+    fn missing_proficiency() {
+        let fixtures =
+            std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/train");
+        let source = crate::mods::ModSource {
+            id: "dda".to_string(),
+            path: fixtures,
+            base: true,
+        };
+        let db = crate::db::make_db_full(&[source], crate::db::LoadMode::Strict).unwrap();
+        // no survival skill at all, and no carving, which doubles the time
+        let input: TrainInput = serde_json::from_value(serde_json::json!({
+            "want_skill": "survival",
+            "forbidden_mats": [],
+            "forbidden_meta": [],
+            "skills": {},
+        }))
+        .unwrap();
+        let path = Path::new("train.json5");
+        let knowledge =
+            super::super::Knowledge::new(&db, path, &input.skills, &[], &[], &[], false);
+        let found = candidates(&db, &input, &knowledge, &[], knowledge.level("survival"));
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].craft_time, "10 m".parse().unwrap());
+        // 55 for the one crafted and 110 for the 10 minutes it really takes
+        assert_eq!(found[0].practice, 165.0);
+    }
+}
@@ -1,33 +1,49 @@
 use crate::db::Db;
 use crate::schema;
+use std::path::Path;
 
-#[allow(dead_code)]
-pub(crate) fn drinks_stuff(db: &Db) {
+pub(crate) mod books;
+pub(crate) mod melee;
+
+fn default_healthy_min() -> i32 {
+    2
+}
+
+#[derive(Debug, serde::Deserialize)]
+#[serde(deny_unknown_fields)]
+struct DrinksInput {
+    #[serde(default = "default_healthy_min")]
+    healthy_min: i32,
+    #[serde(default)]
+    drinks_only: bool,
+}
+
+pub(crate) fn drinks_stuff(db: &Db, input_path: &Path, out_path: &Path) {
+    let input: DrinksInput = crate::read_input(input_path);
     let mut maybe_drinks = vec![];
     for item in &db.raw {
         if item["type"] != "COMESTIBLE" {
             continue;
         }
         let item: crate::schema::Comestible =
-            serde_json::from_value(item.clone()).expect(&format!("{:?}", item["id"]));
-        if item.healthy <= 1 {
+            serde_json::from_value(item.clone()).unwrap_or_else(|_| panic!("{:?}", item["id"]));
+        if item.healthy < input.healthy_min {
             continue;
         }
-        if item.comestible_type != "DRINK" {
-            // continue
+        if input.drinks_only && item.comestible_type != "DRINK" {
+            continue;
         }
         maybe_drinks.push(item);
     }
     maybe_drinks.sort_unstable_by_key(|d| d.healthy);
+    let mut tbl = crate::table::Table::new();
+    tbl.set_headers(row!["name", "calories", "quench", "healthy"]);
     for d in maybe_drinks {
-        println!(
-            "{:>25}  {:5}  {:3}  {:3}",
-            d.name.as_str(),
-            d.calories,
-            d.quench,
-            d.healthy
-        )
+        tbl.add_row(row![d.name.translated(), d.calories, d.quench, d.healthy]);
     }
+    use std::io::Write;
+    let mut out_file = crate::create_output(out_path);
+    out_file.write_all(tbl.format().as_bytes()).unwrap();
 }
 
 fn extract_js_one<'a>(jo: &'a serde_json::Value, descr: &str) -> &'a serde_json::Value {
@@ -56,6 +72,66 @@ fn show_json_value_plain(jv: &serde_json::Value) -> String {
     }
 }
 
+// Sorts by each of `sort_by` in turn, the first one mattering most. Numbers sort as numbers.
+// A leading "-" sorts that one descending, "-dps" is the biggest dps first.
+fn sort_by_columns<T: serde::Serialize>(stats: &mut [T], sort_by: &[String]) {
+    for sort_by in sort_by.iter().rev() {
+        if stats.is_empty() {
+            // idk
+            continue;
+        }
+        let (sort_by, descending) = match sort_by.strip_prefix('-') {
+            Some(column) => (column, true),
+            None => (sort_by.as_str(), false),
+        };
+        let order = |ordering: std::cmp::Ordering| {
+            if descending {
+                ordering.reverse()
+            } else {
+                ordering
+            }
+        };
+        match serde_json::to_value(&stats[0]).unwrap()[sort_by] {
+            serde_json::Value::Number(_) => {
+                let key = |s: &T| {
+                    ordered_float::OrderedFloat(
+                        serde_json::to_value(s).unwrap()[sort_by].as_f64().unwrap(),
+                    )
+                };
+                stats.sort_by(|a, b| order(key(a).cmp(&key(b))));
+            }
+            _ => {
+                let key = |s: &T| {
+                    serde_json::to_string(&serde_json::to_value(s).unwrap()[sort_by]).unwrap()
+                };
+                stats.sort_by(|a, b| order(key(a).cmp(&key(b))));
+            }
+        };
+    }
+}
+
+// A row per stat, a column per `columns`: paths into the serialized stat like "item.inner.warmth",
+// with "|" separating fallbacks. Headers are the last path segments.
+fn columns_table<T: serde::Serialize>(stats: &[T], columns: &[String]) -> crate::table::Table {
+    let mut tbl = crate::table::Table::new();
+    tbl.set_headers(
+        columns
+            .iter()
+            .map(|c| c.as_str().rsplit(".").next().unwrap().to_string())
+            .collect::<Vec<_>>(),
+    );
+    for stat in stats {
+        let stat_ser = serde_json::to_value(stat).unwrap();
+        let mut row = vec![];
+        for column_desc in columns {
+            let column = extract_js_full(&stat_ser, column_desc);
+            row.push(show_json_value_plain(column));
+        }
+        tbl.add_row(row);
+    }
+    tbl
+}
+
 #[derive(Debug, serde::Deserialize)]
 struct BootsInput {
     covers_all: Vec<String>,
@@ -70,8 +146,7 @@ struct BootsInput {
     allow_unobtainable: bool,
 }
 
-#[allow(dead_code)]
-pub(super) fn boots_stuff(db: &Db) {
+pub(super) fn boots_stuff(db: &Db, input_path: &Path, out_path: &Path) {
     let mut items = vec![];
     for item in &db.raw {
         if item["type"] != "ARMOR" {
@@ -83,10 +158,11 @@ pub(super) fn boots_stuff(db: &Db) {
         items.push(item.clone());
     }
 
-    let input: BootsInput =
-        json5::from_str(&std::fs::read_to_string("input/boots.json5").unwrap()).unwrap();
+    let input: BootsInput = crate::read_input(input_path);
     #[derive(Clone, serde::Serialize)]
     struct Stat<T> {
+        // as shown in the game, in the language picked
+        name: String,
         item: schema::CataItem,
         bash: i32,
         cut: i32,
@@ -99,12 +175,12 @@ pub(super) fn boots_stuff(db: &Db) {
     'l: for item in items {
         let armor = item.as_armor();
         for cover in &input.covers_all {
-            if !armor.covers.contains(&cover) {
+            if !armor.covers.contains(cover) {
                 continue 'l;
             }
         }
         for cover in &input.covers_none {
-            if armor.covers.contains(&cover) {
+            if armor.covers.contains(cover) {
                 continue 'l;
             }
         }
@@ -122,7 +198,7 @@ pub(super) fn boots_stuff(db: &Db) {
             println!(
                 "{} ({}) is unobtainable",
                 item.header.id,
-                item.header.name.as_str()
+                item.header.name.translated()
             );
             continue;
         }
@@ -132,10 +208,8 @@ pub(super) fn boots_stuff(db: &Db) {
             .material
             .iter()
             .map(|mat| {
-                db.materials
-                    .iter()
-                    .find(|dbm: &&schema::Material| &dbm.id == mat)
-                    .unwrap()
+                db.lookup_material(mat)
+                    .unwrap_or_else(|| panic!("no material {:?}", mat))
             })
             .collect();
         let avg_bash =
@@ -147,7 +221,7 @@ pub(super) fn boots_stuff(db: &Db) {
 
         let mut enc = armor.encumbrance;
         if item.header.flags.contains(&"VARSIZE".to_string()) {
-            enc = enc / 2; // round down;
+            enc /= 2; // round down;
         } // this is not actually real but i want to sort easily
 
         let bash = (avg_bash * armor.material_thickness as f32).round() as i32;
@@ -166,6 +240,7 @@ pub(super) fn boots_stuff(db: &Db) {
         let total = bash + cut + acid;
 
         stats.push(Stat {
+            name: item.header.name.translated().to_string(),
             item,
             bash,
             cut,
@@ -176,117 +251,237 @@ pub(super) fn boots_stuff(db: &Db) {
         });
     }
 
-    for sort_by in input.sort_by.as_slice().iter().rev() {
-        if stats.len() == 0 {
-            // idk
-            continue;
-        }
-        match serde_json::to_value(stats[0].clone()).unwrap()[&sort_by] {
-            serde_json::Value::Number(_) => {
-                stats.sort_by_key(|s| {
-                    ordered_float::OrderedFloat(
-                        serde_json::to_value(s).unwrap()[&sort_by].as_f64().unwrap(),
-                    )
-                });
-            }
-            _ => stats.sort_by_key(|s| {
-                serde_json::to_string(&serde_json::to_value(s).unwrap()[&sort_by]).unwrap()
-            }),
-        };
-    }
-    //
-
-    let mut tbl = crate::table::Table::new();
-    tbl.set_headers(row![
-        "id", "name", "mats", "thick", "bash", "cut", "acid", "enc", "good",
-    ]);
-    tbl.set_headers(
-        input
-            .columns
-            .iter()
-            .map(|c| c.as_str().rsplit(".").next().unwrap().to_string())
-            .collect::<Vec<_>>(),
-    );
-    for stat in stats {
-        let stat_ser = serde_json::to_value(stat.clone()).unwrap();
-        let mut row = vec![];
-        for column_desc in &input.columns {
-            let column = extract_js_full(&stat_ser, column_desc);
-            row.push(show_json_value_plain(column));
-        }
-        // println!("{}", serde_json::to_string_pretty(&stat_ser).unwrap());
-        tbl.add_row(row);
-
-        // let item = stat.item;
-        // let armor = item.as_armor();
-        // tbl.add_row(row![
-        //     item.header.id,
-        //     item.header.name.as_str().to_string(),
-        //     armor.material.join(","),
-        //     armor.material_thickness,
-        //     stat.bash,
-        //     stat.cut,
-        //     stat.acid,
-        //     stat.encumbrance,
-        //     format!("{:?}", stat.goodness),
-        // ]);
-
-        // println!(
-        //     "{:>35} {:>23} {:>2} {:>2.0} {:>3.0} {:>2} {:>2} {:?}",
-        //     item.header.name.as_str(),
-        //     armor.material.join(","),
-        // armor.material_thickness,
-        // stat.bash,
-        // stat.cut,
-        // stat.acid,
-        // stat.enc,
-        // stat.goodness,
-        // );
-    }
+    sort_by_columns(&mut stats, &input.sort_by);
+    let tbl = columns_table(&stats, &input.columns);
     use std::io::Write;
-    let mut out_file = std::fs::OpenOptions::new()
-        .create(true)
-        .truncate(true)
-        .write(true)
-        .open("out/boots.txt")
-        .unwrap();
+    let mut out_file = crate::create_output(out_path);
     out_file.write_all(tbl.format().as_bytes()).unwrap();
     // println!("{}", tbl.format());
 }
 
-fn attack_time(item: &schema::CataItem) -> Option<i32> {
-    let time = (65.0
-        + (item.header.volume?.ml as f32 / 62.5 + item.header.weight?.g as f32 / 60 as f32))
-        as i32;
-    return Some(time);
+fn default_ranges() -> Vec<i32> {
+    vec![5, 10, 20]
 }
-#[allow(dead_code)]
-pub(crate) fn swords_stuff(db: &Db) {
-    let mut times = vec![];
-    for raw in &db.raw {
-        if !["GENERIC", "TOOL"].contains(&raw["type"].as_str().unwrap()) {
-            continue;
+
+fn default_skill_level() -> i32 {
+    4
+}
+
+#[derive(Debug, serde::Deserialize)]
+#[serde(deny_unknown_fields)]
+struct GunsInput {
+    // only guns using these skills, e.g. "rifle"; all if empty
+    #[serde(default)]
+    skills: Vec<String>,
+    // only these ammo types, e.g. "9mm"; all if empty
+    #[serde(default)]
+    ammo_types: Vec<String>,
+    #[serde(default)]
+    allow_unobtainable: bool,
+    // how good the shooter is with the gun's skill, for the time an attack takes
+    #[serde(default = "default_skill_level")]
+    skill_level: i32,
+    // distances in tiles to get "dps_at_<distance>" columns for
+    #[serde(default = "default_ranges")]
+    ranges: Vec<i32>,
+    columns: Vec<String>,
+    sort_by: Vec<String>,
+}
+
+// How long an attack with a gun of `skill` takes, in moves, see "time_to_attack" of skills.
+fn attack_moves(db: &Db, skill: &str, level: i32) -> i64 {
+    let time = db
+        .lookup("skill", skill)
+        .map(|skill| &skill["time_to_attack"])
+        .filter(|time| time.is_object());
+    let time = match time {
+        Some(time) => time,
+        None => return 100,
+    };
+    let get = |key: &str| time[key].as_i64().unwrap_or(0);
+    (get("base_time") - get("time_reduction_per_level") * level as i64).max(get("min_time"))
+}
+
+// A rough chance to hit a person sized target `distance` tiles away: the shot goes anywhere
+// within the dispersion (arcminutes) around the aim, and hits if it passes within half a tile.
+fn hit_chance(dispersion: i32, distance: i32) -> f32 {
+    let needed = (0.5 / distance.max(1) as f32).atan().to_degrees() * 60.0;
+    (needed / dispersion.max(1) as f32).min(1.0)
+}
+
+// for the table, which shows floats in full
+fn round2(x: f32) -> f64 {
+    (x as f64 * 100.0).round() / 100.0
+}
+
+pub(crate) fn guns_stuff(db: &Db, input_path: &Path, out_path: &Path) {
+    let input: GunsInput = crate::read_input(input_path);
+    // None for an entry that doesn't make sense, which is left out with a warning
+    let parse = |raw: &serde_json::Value| -> Option<schema::CataItem> {
+        match serde_json::from_value(raw.clone()) {
+            Ok(item) => Some(item),
+            Err(e) => {
+                log::warn!("skipping {} {}: {}", raw["type"], raw["id"], e);
+                None
+            }
+        }
+    };
+    let obtainable =
+        |item: &schema::CataItem| input.allow_unobtainable || db.is_obtainable(&item.header.id);
+
+    let mut ammo_by_type: std::collections::HashMap<String, Vec<schema::CataItem>> =
+        Default::default();
+    for raw in db.raw.iter().filter(|raw| raw["type"] == "AMMO") {
+        let ammo = match parse(raw) {
+            Some(ammo) => ammo,
+            None => continue,
+        };
+        if obtainable(&ammo) {
+            let ammo_type = ammo.as_ammo().ammo_type.clone();
+            ammo_by_type.entry(ammo_type).or_default().push(ammo);
         }
-        let item: schema::CataItem =
-            serde_json::from_value(raw.clone()).unwrap_or_else(|e| panic!("{}:\n{:#}", e, raw));
-        if item.header.category.as_deref() != Some("weapons") {
+    }
+
+    #[derive(Clone, serde::Serialize)]
+    struct Stat {
+        // as shown in the game, in the language picked
+        name: String,
+        ammo: String,
+        gun_id: String,
+        ammo_id: String,
+        skill: String,
+        damage: f64,
+        armor_penetration: f64,
+        dispersion: i32,
+        range: i32,
+        // the biggest magazine that fits, or "integral"
+        magazine: String,
+        shots: i32,
+        // per attack, in the mode firing the most
+        burst: i32,
+        attack_moves: i64,
+        reload_moves: i64,
+        reload: String,
+        // emptying a magazine and reloading, over and over, if every shot hits
+        dps: f64,
+        #[serde(flatten)]
+        dps_at: std::collections::BTreeMap<String, f64>,
+    }
+
+    let mut stats = vec![];
+    for raw in db.raw.iter().filter(|raw| raw["type"] == "GUN") {
+        let item = match parse(raw) {
+            Some(item) => item,
+            None => continue,
+        };
+        let gun = item.as_gun();
+        if !input.skills.is_empty() && !input.skills.contains(&gun.skill) {
             continue;
         }
-        let time = if let Some(t) = attack_time(&item) {
-            t
-        } else {
+        if !obtainable(&item) {
             continue;
-        };
-        times.push((item, time));
-    }
-    times.sort_by_key(|x| x.1);
-    for (item, time) in times {
-        println!(
-            "{:>4} {:>4} {:>4} - {:?}",
-            time,
-            item.header.bashing,
-            item.header.cutting,
-            item.header.name.as_str()
-        );
+        }
+        for ammo_type in &gun.ammo {
+            if !input.ammo_types.is_empty() && !input.ammo_types.contains(ammo_type) {
+                continue;
+            }
+            // rounds that go straight into the gun, or else the biggest magazine for them,
+            // which is filled a round at a time and then put in
+            let integral: i32 = gun
+                .pocket_data
+                .iter()
+                .filter(|p| p.pocket_type.as_deref() == Some("MAGAZINE"))
+                .filter_map(|p| p.ammo_restriction.get(ammo_type))
+                .sum();
+            let (magazine, shots, reload) = if integral > 0 {
+                (
+                    "integral".to_string(),
+                    integral,
+                    gun.reload * integral as i64,
+                )
+            } else {
+                let magazines = gun.compatible_magazines().into_iter().filter_map(|id| {
+                    let entry = parse(db.lookup_item(id)?)?;
+                    let magazine = match entry.try_as_magazine() {
+                        Some(magazine) => magazine,
+                        None => {
+                            log::warn!(
+                                "skipping magazine {:?} of {}: not a MAGAZINE",
+                                id,
+                                item.header.id
+                            );
+                            return None;
+                        }
+                    };
+                    let shots = magazine.capacity_for(ammo_type);
+                    let reload = gun.reload + magazine.reload_time * shots as i64;
+                    Some((entry.header.name.translated().to_string(), shots, reload))
+                });
+                match magazines.max_by_key(|(_, shots, _)| *shots) {
+                    Some(best) => best,
+                    None => continue,
+                }
+            };
+            if shots == 0 {
+                continue;
+            }
+            let burst = gun
+                .modes
+                .iter()
+                .map(|m| m.shots)
+                .max()
+                .unwrap_or(1)
+                .min(shots);
+            let attack_moves = attack_moves(db, &gun.skill, input.skill_level);
+            let attacks = (shots + burst - 1) / burst;
+            let cycle_moves = attacks as i64 * attack_moves + reload.moves();
+
+            for ammo in ammo_by_type.get(ammo_type).into_iter().flatten() {
+                let rounds = ammo.as_ammo();
+                let damage = gun.ranged_damage.total() + rounds.damage.total();
+                let dispersion = gun.dispersion + gun.sight_dispersion + rounds.dispersion;
+                let range = gun.range + rounds.range;
+                let dps = damage * shots as f32 / (cycle_moves.max(1) as f32 / 100.0);
+                let dps_at = input
+                    .ranges
+                    .iter()
+                    .map(|&distance| {
+                        let hits = if distance > range {
+                            0.0
+                        } else {
+                            hit_chance(dispersion, distance)
+                        };
+                        (format!("dps_at_{}", distance), round2(dps * hits))
+                    })
+                    .collect();
+                stats.push(Stat {
+                    name: item.header.name.translated().to_string(),
+                    ammo: ammo.header.name.translated().to_string(),
+                    gun_id: item.header.id.clone(),
+                    ammo_id: ammo.header.id.clone(),
+                    skill: gun.skill.clone(),
+                    damage: round2(damage),
+                    armor_penetration: round2(
+                        gun.ranged_damage.armor_penetration() + rounds.damage.armor_penetration(),
+                    ),
+                    dispersion,
+                    range,
+                    magazine: magazine.clone(),
+                    shots,
+                    burst,
+                    attack_moves,
+                    reload_moves: reload.moves(),
+                    reload: reload.to_string(),
+                    dps: round2(dps),
+                    dps_at,
+                });
+            }
+        }
     }
+
+    sort_by_columns(&mut stats, &input.sort_by);
+    let tbl = columns_table(&stats, &input.columns);
+    use std::io::Write;
+    let mut out_file = crate::create_output(out_path);
+    out_file.write_all(tbl.format().as_bytes()).unwrap();
 }
@@ -0,0 +1,129 @@
+// Skill books worth reading: the ones the character can still learn something from,
+// how long one reading session takes them, what recipes come with the book and where it turns up.
+use crate::db::Db;
+use crate::schema;
+use std::path::Path;
+
+type Map<K, V> = std::collections::HashMap<K, V>;
+
+fn default_intelligence() -> i32 {
+    8
+}
+
+#[derive(Debug, serde::Deserialize)]
+#[serde(deny_unknown_fields)]
+struct BooksInput {
+    skills: Map<String, i32>,
+    #[serde(default = "default_intelligence")]
+    intelligence: i32,
+    // only books for these, all skills if empty
+    #[serde(default)]
+    want_skills: Vec<String>,
+    #[serde(default)]
+    allow_unobtainable: bool,
+    columns: Vec<String>,
+    sort_by: Vec<String>,
+}
+
+// A book minute takes a minute at 8 intelligence, 5% less for every point above that
+// and 5% more for every point below, but never under a tenth. A book smarter than the reader
+// adds another second per book minute for every point of difference.
+fn reading_time(book: &schema::Book, intelligence: i32) -> schema::Duration {
+    let speed = (1.0 - 0.05 * (intelligence - 8) as f64).max(0.1);
+    let mut time = book.time.scaled(speed);
+    if book.intelligence > intelligence {
+        time += book
+            .time
+            .scaled((book.intelligence - intelligence) as f64 / 60.0);
+    }
+    time
+}
+
+pub(crate) fn books_stuff(db: &Db, input_path: &Path, out_path: &Path) {
+    let input: BooksInput = crate::read_input(input_path);
+
+    // book -> what it teaches, and at which skill level
+    let mut taught: Map<&str, Vec<(&str, i32)>> = Map::new();
+    for recipe in &db.recipes {
+        for (book, level) in &recipe.book_learn {
+            let recipes = taught.entry(book).or_default();
+            recipes.push((&recipe.result, *level));
+        }
+    }
+
+    #[derive(Clone, serde::Serialize)]
+    struct Stat {
+        // as shown in the game, in the language picked
+        name: String,
+        id: String,
+        skill: String,
+        required_level: i32,
+        max_level: i32,
+        intelligence: i32,
+        // still to gain from it
+        levels: i32,
+        // of one reading session, for this character
+        minutes: f64,
+        time: String,
+        recipes: String,
+        spawns: String,
+    }
+
+    let mut stats = vec![];
+    for raw in &db.raw {
+        if raw["type"].as_str() != Some("BOOK") {
+            continue;
+        }
+        let item: schema::CataItem =
+            serde_json::from_value(raw.clone()).unwrap_or_else(|e| panic!("{}:\n{:#}", e, raw));
+        let book = item.as_book();
+        let skill = match &book.skill {
+            Some(skill) => skill,
+            None => continue,
+        };
+        if !input.want_skills.is_empty() && !input.want_skills.contains(skill) {
+            continue;
+        }
+        let level = input.skills.get(skill).copied().unwrap_or(0);
+        if level < book.required_level || level >= book.max_level {
+            continue;
+        }
+        if !input.allow_unobtainable && !db.is_obtainable(&item.header.id) {
+            continue;
+        }
+
+        let time = reading_time(book, input.intelligence);
+        let mut recipes = taught
+            .get(item.header.id.as_str())
+            .cloned()
+            .unwrap_or_default();
+        recipes.sort_by_key(|(result, level)| (*level, *result));
+        let recipes: Vec<String> = recipes
+            .iter()
+            .map(|(result, level)| format!("{}@{}", result, level))
+            .collect();
+        let spawns: Vec<&str> = db
+            .itemgroups_with(&item.header.id)
+            .map(|group| group.id.as_str())
+            .collect();
+        stats.push(Stat {
+            name: item.header.name.translated().to_string(),
+            id: item.header.id.clone(),
+            skill: skill.clone(),
+            required_level: book.required_level,
+            max_level: book.max_level,
+            intelligence: book.intelligence,
+            levels: book.max_level - level,
+            minutes: super::round2(time.seconds() as f32 / 60.0),
+            time: time.to_string(),
+            recipes: recipes.join(","),
+            spawns: spawns.join(","),
+        });
+    }
+
+    super::sort_by_columns(&mut stats, &input.sort_by);
+    let tbl = super::columns_table(&stats, &input.columns);
+    use std::io::Write;
+    let mut out_file = crate::create_output(out_path);
+    out_file.write_all(tbl.format().as_bytes()).unwrap();
+}
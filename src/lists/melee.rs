@@ -0,0 +1,378 @@
+// Melee weapons compared by expected damage per second, for a given character against a given target.
+// Follows the game's formulas where they are simple and averages over the dice where they aren't:
+// attack cost from size, melee skill and dexterity; hitting from to-hit, dexterity and skill
+// against the target's dodge; damage from the weapon, strength and the bashing, cutting and
+// stabbing skills, less the target's armor; weapon techniques (and those of a martial art)
+// picked at random with the plain attack. Crits and stamina are left out.
+use super::round2;
+use crate::db::Db;
+use crate::schema;
+use std::path::Path;
+
+type Map<K, V> = std::collections::HashMap<K, V>;
+
+fn default_category() -> String {
+    "weapons".to_string()
+}
+
+fn default_stat() -> i32 {
+    8
+}
+
+#[derive(Debug, serde::Deserialize)]
+#[serde(deny_unknown_fields)]
+struct Stats {
+    #[serde(default = "default_stat")]
+    str: i32,
+    #[serde(default = "default_stat")]
+    dex: i32,
+}
+
+impl Default for Stats {
+    fn default() -> Self {
+        Stats {
+            str: default_stat(),
+            dex: default_stat(),
+        }
+    }
+}
+
+// what is being hit: how well it dodges and how much of each damage type its armor stops
+#[derive(Debug, Default, serde::Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct Target {
+    dodge: i32,
+    bash: i32,
+    cut: i32,
+    stab: i32,
+}
+
+#[derive(Debug, serde::Deserialize)]
+#[serde(deny_unknown_fields)]
+struct MeleeInput {
+    #[serde(default = "default_category")]
+    category: String,
+    #[serde(default)]
+    allow_unobtainable: bool,
+    #[serde(default)]
+    stats: Stats,
+    // melee, bashing, cutting and stabbing; missing ones are 0
+    #[serde(default)]
+    skills: Map<String, i32>,
+    martial_art: Option<String>,
+    #[serde(default)]
+    target: Target,
+    columns: Vec<String>,
+    sort_by: Vec<String>,
+}
+
+// A technique's change to an attack, e.g. ["damage", "bash", 0.66] or
+// {"stat": "movecost", "scale": 0.5}.
+#[derive(Debug, serde::Deserialize)]
+#[serde(untagged)]
+enum Bonus {
+    Typed(String, String, f32),
+    Plain(String, f32),
+    Described {
+        stat: String,
+        #[serde(rename = "type")]
+        typ: Option<String>,
+        scale: f32,
+    },
+}
+
+impl Bonus {
+    fn parts(&self) -> (&str, Option<&str>, f32) {
+        match self {
+            Bonus::Typed(stat, typ, scale) => (stat, Some(typ), *scale),
+            Bonus::Plain(stat, scale) => (stat, None, *scale),
+            Bonus::Described { stat, typ, scale } => (stat, typ.as_deref(), *scale),
+        }
+    }
+}
+
+#[derive(Debug, serde::Deserialize)]
+struct SkillRequirement {
+    name: String,
+    level: i32,
+}
+
+#[derive(Debug, serde::Deserialize)]
+struct Technique {
+    #[serde(default)]
+    crit_tec: bool,
+    #[serde(default)]
+    defensive: bool,
+    #[serde(default)]
+    min_melee: i32,
+    #[serde(default)]
+    skill_requirements: Vec<SkillRequirement>,
+    #[serde(default)]
+    mult_bonuses: Vec<Bonus>,
+    #[serde(default)]
+    flat_bonuses: Vec<Bonus>,
+}
+
+impl Technique {
+    // Only plain attacks that the character can do count: crits aren't modelled,
+    // and defensive techniques are for blocking and dodging.
+    fn usable(&self, skills: &Map<String, i32>) -> bool {
+        let level = |skill: &str| skills.get(skill).copied().unwrap_or(0);
+        !self.crit_tec
+            && !self.defensive
+            && level("melee") >= self.min_melee
+            && self
+                .skill_requirements
+                .iter()
+                .all(|r| level(&r.name) >= r.level)
+    }
+}
+
+#[derive(Debug, Default, serde::Deserialize)]
+struct MartialArt {
+    #[serde(default)]
+    techniques: Vec<String>,
+    #[serde(default)]
+    weapons: Vec<String>,
+    #[serde(default)]
+    weapon_category: Vec<String>,
+}
+
+const DAMAGE_TYPES: &[&str] = &["bash", "cut", "stab"];
+
+// One way of attacking: how long it takes and how much of each damage type it deals before armor.
+#[derive(Debug, Clone)]
+struct Attack {
+    moves: f32,
+    damage: Map<&'static str, f32>,
+}
+
+impl Attack {
+    fn with_technique(&self, technique: &Technique) -> Attack {
+        let mut attack = self.clone();
+        for (bonuses, multiply) in [
+            (&technique.mult_bonuses, true),
+            (&technique.flat_bonuses, false),
+        ] {
+            for bonus in bonuses {
+                let (stat, typ, scale) = bonus.parts();
+                let apply = |value: &mut f32| {
+                    if multiply {
+                        *value *= scale
+                    } else {
+                        *value += scale
+                    }
+                };
+                match (stat, typ) {
+                    ("movecost", _) => apply(&mut attack.moves),
+                    ("damage", Some(typ)) => {
+                        if let Some(damage) = attack.damage.get_mut(typ) {
+                            apply(damage)
+                        }
+                    }
+                    _ => {}
+                }
+            }
+        }
+        attack
+    }
+
+    fn after_armor(&self, target: &Target) -> f32 {
+        let armor = |typ: &str| match typ {
+            "bash" => target.bash,
+            "cut" => target.cut,
+            _ => target.stab,
+        };
+        DAMAGE_TYPES
+            .iter()
+            .map(|typ| (self.damage[typ] - armor(typ) as f32).max(0.0))
+            .sum()
+    }
+}
+
+// P(X < x) for the standard normal distribution, Abramowitz and Stegun 7.1.26
+fn normal_cdf(x: f32) -> f32 {
+    let z = x.abs() / std::f32::consts::SQRT_2;
+    let t = 1.0 / (1.0 + 0.327_591_1 * z);
+    let poly = t
+        * (0.254_829_6
+            + t * (-0.284_496_7 + t * (1.421_413_7 + t * (-1.453_152 + t * 1.061_405_4))));
+    let erf = 1.0 - poly * (-z * z).exp();
+    if x >= 0.0 {
+        (1.0 + erf) / 2.0
+    } else {
+        (1.0 - erf) / 2.0
+    }
+}
+
+// Both sides roll normal(5 * skill, 25), the attacker hits if they roll higher.
+fn hit_chance(hit: f32, dodge: f32) -> f32 {
+    normal_cdf(5.0 * (hit - dodge) / (25.0 * std::f32::consts::SQRT_2))
+}
+
+fn skill_multiplier(level: i32) -> f32 {
+    0.8 + 0.08 * level.min(10) as f32
+}
+
+// the plain attack with `item`, before techniques
+fn plain_attack(
+    item: &schema::CataItem,
+    stats: &Stats,
+    skills: &Map<String, i32>,
+) -> Option<Attack> {
+    let level = |skill: &str| skills.get(skill).copied().unwrap_or(0);
+    let header = &item.header;
+
+    let volume = header.volume?.ml() as f32;
+    let weight = header.weight?.grams() as f32;
+    let attack_time = 65.0 + volume / 62.5 + weight / 60.0;
+    let base = attack_time / 2.0;
+    let skill_cost = base * (15 - level("melee")) as f32 / 15.0;
+    // dexterity takes off 0 to dex moves
+    let moves = (base + skill_cost - stats.dex as f32 / 2.0).max(25.0);
+
+    let listed = |typ: &str, old: i32| header.melee_damage.get(typ).copied().unwrap_or(old as f32);
+    let mut bash = listed("bash", header.bashing);
+    let mut cut = listed("cut", header.cutting);
+    let mut stab = listed("stab", 0);
+    // spears and knives stab with their edge
+    if header.flags.iter().any(|f| f == "SPEAR" || f == "STAB") {
+        stab += cut;
+        cut = 0.0;
+    }
+    if bash + cut + stab <= 0.0 {
+        return None;
+    }
+
+    // strength adds three quarters of itself, less past what the character can make use of
+    let with_strength = bash + stats.str as f32 * 0.75;
+    let cap = (2 * stats.str + 2 * level("bashing")) as f32;
+    bash = with_strength;
+    if cap < with_strength {
+        bash *= (1.0 + cap / with_strength) / 2.0;
+    }
+    bash *= skill_multiplier(level("bashing"));
+    cut *= skill_multiplier(level("cutting"));
+    stab *= skill_multiplier(level("stabbing"));
+
+    let damage = [("bash", bash), ("cut", cut), ("stab", stab)];
+    Some(Attack {
+        moves,
+        damage: damage.iter().cloned().collect(),
+    })
+}
+
+pub(crate) fn melee_stuff(db: &Db, input_path: &Path, out_path: &Path) {
+    let input: MeleeInput = crate::read_input(input_path);
+    let level = |skill: &str| input.skills.get(skill).copied().unwrap_or(0);
+    let martial_art: MartialArt = match &input.martial_art {
+        Some(id) => {
+            let style = db.lookup("martial_art", id).unwrap_or_else(|| {
+                eprintln!(
+                    "error: {}: martial_art: no martial art {:?}",
+                    input_path.display(),
+                    id
+                );
+                std::process::exit(1)
+            });
+            serde_json::from_value(style.clone()).unwrap_or_else(|e| panic!("{}:\n{:#}", e, style))
+        }
+        None => MartialArt::default(),
+    };
+    let technique = |id: &str| -> Option<Technique> {
+        let raw = db.lookup("technique", id)?;
+        Some(serde_json::from_value(raw.clone()).unwrap_or_else(|e| panic!("{}:\n{:#}", e, raw)))
+    };
+
+    #[derive(Clone, serde::Serialize)]
+    struct Stat {
+        // as shown in the game, in the language picked
+        name: String,
+        id: String,
+        to_hit: i32,
+        // per attack, without techniques
+        moves: f64,
+        hit_chance: f64,
+        bash: f64,
+        cut: f64,
+        stab: f64,
+        // per attack that hits, after armor, techniques included
+        damage: f64,
+        dps: f64,
+        techniques: String,
+    }
+
+    let mut stats = vec![];
+    for raw in &db.raw {
+        if !["GENERIC", "TOOL"].contains(&raw["type"].as_str().unwrap()) {
+            continue;
+        }
+        let item: schema::CataItem =
+            serde_json::from_value(raw.clone()).unwrap_or_else(|e| panic!("{}:\n{:#}", e, raw));
+        if item.header.category.as_deref() != Some(input.category.as_str()) {
+            continue;
+        }
+        if !input.allow_unobtainable && !db.is_obtainable(&item.header.id) {
+            continue;
+        }
+        let plain = match plain_attack(&item, &input.stats, &input.skills) {
+            Some(attack) => attack,
+            None => continue,
+        };
+
+        let mut technique_ids = item.header.techniques.clone();
+        let in_style = martial_art.weapons.contains(&item.header.id)
+            || item
+                .header
+                .weapon_category
+                .iter()
+                .any(|c| martial_art.weapon_category.contains(c));
+        if in_style {
+            technique_ids.extend(martial_art.techniques.iter().cloned());
+        }
+        let mut used = vec![];
+        let mut attacks = vec![plain.clone()];
+        for id in technique_ids {
+            match technique(&id) {
+                Some(technique) if technique.usable(&input.skills) => {
+                    attacks.push(plain.with_technique(&technique));
+                    used.push(id);
+                }
+                _ => {}
+            }
+        }
+
+        let to_hit = item.header.to_hit.value();
+        let hit = input.stats.dex as f32 / 4.0 + level("melee") as f32 + to_hit as f32;
+        let hit_chance = hit_chance(hit, input.target.dodge as f32);
+        // an even pick between the plain attack and each technique, misses take as long as hits
+        let count = attacks.len() as f32;
+        let damage = attacks
+            .iter()
+            .map(|a| a.after_armor(&input.target))
+            .sum::<f32>()
+            / count;
+        let moves = attacks.iter().map(|a| a.moves).sum::<f32>() / count;
+        let dps = hit_chance * damage / (moves / 100.0);
+
+        stats.push(Stat {
+            name: item.header.name.translated().to_string(),
+            id: item.header.id.clone(),
+            to_hit,
+            moves: round2(plain.moves),
+            hit_chance: round2(hit_chance),
+            bash: round2(plain.damage["bash"]),
+            cut: round2(plain.damage["cut"]),
+            stab: round2(plain.damage["stab"]),
+            damage: round2(damage),
+            dps: round2(dps),
+            techniques: used.join(","),
+        });
+    }
+
+    super::sort_by_columns(&mut stats, &input.sort_by);
+    let tbl = super::columns_table(&stats, &input.columns);
+    use std::io::Write;
+    let mut out_file = crate::create_output(out_path);
+    out_file.write_all(tbl.format().as_bytes()).unwrap();
+}
@@ -1,25 +1,247 @@
 #[macro_use]
 mod table;
+mod config;
 mod db;
+mod mods;
 mod schema;
+mod translation;
 
 mod graphs;
 mod lists;
 mod plots;
 
-// const CATA_ROOT: &'static str = r#"D:\games\cataclysm-dda\_this\"#;
-const CATA_ROOT: &'static str = r#"D:\games\cataclysm-dda\launcher-autoupdated\"#;
+use std::path::{Path, PathBuf};
+use structopt::StructOpt;
+
+#[derive(Debug, StructOpt)]
+struct GameOpts {
+    /// Game installation directory, the one containing `data/json`. Defaults to
+    /// $CATA_ROOT, then to `game_root` from config.json5, then to searching the usual places
+    #[structopt(long, parse(from_os_str))]
+    game_root: Option<PathBuf>,
+    /// Mod ids to load on top of the base game, dependencies are pulled in automatically.
+    /// Defaults to `mods` from config.json5
+    #[structopt(long, use_delimiter = true)]
+    mods: Option<Vec<String>>,
+    /// Reload the game data even if the cached copy looks up to date
+    #[structopt(long)]
+    rebuild_cache: bool,
+    /// Show and match item names in this language, e.g. `ru` or `zh_CN`, as found in
+    /// lang/mo of the game. Defaults to `lang` from config.json5, then to English
+    #[structopt(long)]
+    lang: Option<String>,
+    /// Skip game data entries that fail to load instead of stopping at the first one.
+    /// The skipped entries are listed in out/load_errors.txt
+    #[structopt(long)]
+    lenient: bool,
+}
+
+#[derive(Debug, StructOpt)]
+#[structopt(about = "Assorted analyses of Cataclysm: DDA game data")]
+enum Command {
+    /// Crafting graphs, one per input file. Needs graphviz `dot` to render
+    Graph {
+        /// A single json5 input, or a directory to take all of them from
+        #[structopt(long, parse(from_os_str), default_value = "input/graphs")]
+        input: PathBuf,
+        /// Directory to put the `.gv` files (and rendered svgs) into
+        #[structopt(long, parse(from_os_str), default_value = "out")]
+        output: PathBuf,
+        /// Graphviz `dot` to render with, looked up in PATH unless it's a path
+        #[structopt(long, parse(from_os_str), default_value = "dot")]
+        dot: PathBuf,
+        #[structopt(flatten)]
+        game: GameOpts,
+    },
+    /// Recipes good for training a skill
+    Train {
+        #[structopt(long, parse(from_os_str), default_value = "input/training.json5")]
+        input: PathBuf,
+        #[structopt(long, parse(from_os_str), default_value = "out/train.txt")]
+        output: PathBuf,
+        #[structopt(flatten)]
+        game: GameOpts,
+    },
+    /// The recipes practicing each proficiency fastest
+    Proficiencies {
+        #[structopt(long, parse(from_os_str), default_value = "input/proficiencies.json5")]
+        input: PathBuf,
+        #[structopt(long, parse(from_os_str), default_value = "out/proficiencies.txt")]
+        output: PathBuf,
+        #[structopt(flatten)]
+        game: GameOpts,
+    },
+    /// Skill books that can still raise a skill, with reading times and where they spawn
+    Books {
+        #[structopt(long, parse(from_os_str), default_value = "input/books.json5")]
+        input: PathBuf,
+        #[structopt(long, parse(from_os_str), default_value = "out/books.txt")]
+        output: PathBuf,
+        #[structopt(flatten)]
+        game: GameOpts,
+    },
+    /// Armor comparison table
+    Armor {
+        #[structopt(long, parse(from_os_str), default_value = "input/boots.json5")]
+        input: PathBuf,
+        #[structopt(long, parse(from_os_str), default_value = "out/boots.txt")]
+        output: PathBuf,
+        #[structopt(flatten)]
+        game: GameOpts,
+    },
+    /// Melee weapons compared by damage per second
+    Weapons {
+        #[structopt(long, parse(from_os_str), default_value = "input/swords.json5")]
+        input: PathBuf,
+        #[structopt(long, parse(from_os_str), default_value = "out/swords.txt")]
+        output: PathBuf,
+        #[structopt(flatten)]
+        game: GameOpts,
+    },
+    /// Firearm and ammo comparison table, with sustained damage per second
+    Guns {
+        #[structopt(long, parse(from_os_str), default_value = "input/guns.json5")]
+        input: PathBuf,
+        #[structopt(long, parse(from_os_str), default_value = "out/guns.txt")]
+        output: PathBuf,
+        #[structopt(flatten)]
+        game: GameOpts,
+    },
+    /// Healthy comestibles
+    Drinks {
+        #[structopt(long, parse(from_os_str), default_value = "input/drinks.json5")]
+        input: PathBuf,
+        #[structopt(long, parse(from_os_str), default_value = "out/drinks.txt")]
+        output: PathBuf,
+        #[structopt(flatten)]
+        game: GameOpts,
+    },
+    /// Encumbrance vs storage plot of belts and other storage armor
+    Belts {
+        #[structopt(long, parse(from_os_str), default_value = "input/belts.json5")]
+        input: PathBuf,
+        #[structopt(long, parse(from_os_str), default_value = "out/belts.svg")]
+        output: PathBuf,
+        #[structopt(flatten)]
+        game: GameOpts,
+    },
+}
+
+pub(crate) fn read_input<T: serde::de::DeserializeOwned>(path: &Path) -> T {
+    let text = std::fs::read_to_string(path)
+        .unwrap_or_else(|e| panic!("failed to read {}: {}", path.display(), e));
+    json5::from_str(&text).unwrap_or_else(|e| panic!("failed to parse {}: {}", path.display(), e))
+}
+
+pub(crate) fn create_output(path: &Path) -> std::fs::File {
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir).unwrap();
+    }
+    std::fs::OpenOptions::new()
+        .create(true)
+        .truncate(true)
+        .write(true)
+        .open(path)
+        .unwrap_or_else(|e| panic!("failed to open {}: {}", path.display(), e))
+}
+
+// Item ids for what was written into `field` of an input file, see `Db::resolve_item`.
+// Lists everything that didn't resolve before giving up, and warns about typos it guessed at.
+pub(crate) fn resolve_input(
+    db: &db::Db,
+    path: &Path,
+    field: &str,
+    queries: &[String],
+) -> Vec<String> {
+    let mut ids = vec![];
+    let mut failed = false;
+    for query in queries {
+        match db.resolve_item(query) {
+            Ok(resolved) => {
+                if let Some(guess) = resolved.guess {
+                    eprintln!("warning: {}: {}: {}", path.display(), field, guess);
+                }
+                ids.push(resolved.item["id"].as_str().unwrap().to_string())
+            }
+            Err(e) => {
+                eprintln!("error: {}: {}: {}", path.display(), field, e);
+                failed = true;
+            }
+        }
+    }
+    if failed {
+        std::process::exit(1);
+    }
+    ids
+}
 
 fn main() {
     env_logger::from_env(env_logger::Env::default().default_filter_or("debug")).init();
-    let db = &db::load_maybe_compressed();
+    let command = Command::from_args();
+    let game = match &command {
+        Command::Graph { game, .. }
+        | Command::Train { game, .. }
+        | Command::Armor { game, .. }
+        | Command::Proficiencies { game, .. }
+        | Command::Books { game, .. }
+        | Command::Weapons { game, .. }
+        | Command::Guns { game, .. }
+        | Command::Drinks { game, .. }
+        | Command::Belts { game, .. } => game,
+    };
+    let config = config::Config::load();
+    let game_root = match config::find_game_root(game.game_root.as_deref(), &config) {
+        Ok(root) => root,
+        Err(e) => {
+            eprintln!("error: {}", e);
+            std::process::exit(1);
+        }
+    };
+    let mods = game.mods.as_ref().unwrap_or(&config.mods);
+    let sources = match mods::load_order(&game_root, mods) {
+        Ok(sources) => sources,
+        Err(e) => {
+            eprintln!("error: {}", e);
+            std::process::exit(1);
+        }
+    };
+    if let Some(lang) = game.lang.as_ref().or(config.lang.as_ref()) {
+        match translation::Catalog::load(&game_root, lang) {
+            Ok(catalog) => translation::set_catalog(catalog),
+            Err(e) => {
+                eprintln!("error: {}", e);
+                std::process::exit(1);
+            }
+        }
+    }
+    let mode = if game.lenient {
+        db::LoadMode::Lenient
+    } else {
+        db::LoadMode::Strict
+    };
+    let db = &match db::load_maybe_compressed(&game_root, &sources, game.rebuild_cache, mode) {
+        Ok(db) => db,
+        Err(e) => {
+            eprintln!("error: {}", e);
+            eprintln!("(--lenient skips entries that fail to load)");
+            std::process::exit(1);
+        }
+    };
     println!("loaded3");
-    
-
-    // lists::boots_stuff(db);
-    // lists::drinks_stuff(db);
-    // graphs::graphviz_all_inputs(db);
-    graphs::train::train(db);
-    // plots::belts(db);
-    // lists::swords_stuff(db);
+
+    match &command {
+        Command::Graph {
+            input, output, dot, ..
+        } => graphs::graphviz_all_inputs(db, input, output, dot),
+        Command::Train { input, output, .. } => graphs::train::train(db, input, output),
+        Command::Armor { input, output, .. } => lists::boots_stuff(db, input, output),
+        Command::Proficiencies { input, output, .. } => {
+            graphs::proficiencies::proficiencies(db, input, output)
+        }
+        Command::Books { input, output, .. } => lists::books::books_stuff(db, input, output),
+        Command::Weapons { input, output, .. } => lists::melee::melee_stuff(db, input, output),
+        Command::Guns { input, output, .. } => lists::guns_stuff(db, input, output),
+        Command::Drinks { input, output, .. } => lists::drinks_stuff(db, input, output),
+        Command::Belts { input, output, .. } => plots::belts(db, input, output),
+    }
 }
@@ -0,0 +1,120 @@
+use crate::schema::ModInfo;
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+};
+
+// The base game, always loaded first and in this order: data/core has the few things
+// the game itself relies on (water, fire, ...), data/json is everything else.
+// Newer versions describe data/json with a core modinfo of id "dda",
+// older ones just always load it.
+const BASE_DIRS: &[(&str, &str)] = &[("core", "data/core"), ("dda", "data/json")];
+
+#[derive(Clone, Debug)]
+pub(crate) struct ModSource {
+    pub id: String,
+    pub path: PathBuf,
+    // part of the base game rather than a mod
+    pub base: bool,
+}
+
+fn read_modinfos(cata_root: &Path) -> Result<HashMap<String, (ModInfo, PathBuf)>, String> {
+    let mods_root = cata_root.join("data").join("mods");
+    let mut out = HashMap::new();
+    if !mods_root.is_dir() {
+        return Ok(out);
+    }
+    for entry in walkdir::WalkDir::new(&mods_root) {
+        let entry = entry.map_err(|e| format!("failed to list {}: {}", mods_root.display(), e))?;
+        if entry.file_name() != "modinfo.json" {
+            continue;
+        }
+        let filename = entry.path();
+        let text = std::fs::read_to_string(filename)
+            .map_err(|e| format!("failed to read {}: {}", filename.display(), e))?;
+        let parsed: serde_json::Value = serde_json::from_str(&text)
+            .map_err(|e| format!("failed to parse {}: {}", filename.display(), e))?;
+        let parsed = match parsed {
+            serde_json::Value::Array(a) => a,
+            x => vec![x],
+        };
+        for info in parsed.into_iter().filter(|x| x["type"] == "MOD_INFO") {
+            let info: ModInfo = serde_json::from_value(info)
+                .map_err(|e| format!("bad MOD_INFO in {}: {}", filename.display(), e))?;
+            let mod_dir = filename.parent().unwrap();
+            let path = match &info.path {
+                Some(p) => mod_dir.join(p),
+                None => mod_dir.to_owned(),
+            };
+            out.insert(info.id.clone(), (info, path));
+        }
+    }
+    Ok(out)
+}
+
+// Base game first, then every wanted mod after all of its dependencies,
+// otherwise keeping the order the mods were asked for in.
+pub(crate) fn load_order(cata_root: &Path, wanted: &[String]) -> Result<Vec<ModSource>, String> {
+    let modinfos = read_modinfos(cata_root)?;
+    let mut order: Vec<ModSource> = BASE_DIRS
+        .iter()
+        .map(|(id, dir)| ModSource {
+            id: id.to_string(),
+            path: cata_root.join(dir),
+            base: true,
+        })
+        .filter(|source| source.path.is_dir())
+        .collect();
+
+    fn visit(
+        id: &str,
+        modinfos: &HashMap<String, (ModInfo, PathBuf)>,
+        order: &mut Vec<ModSource>,
+        stack: &mut Vec<String>,
+    ) -> Result<(), String> {
+        if order.iter().any(|m| m.id == id) {
+            return Ok(());
+        }
+        if stack.iter().any(|s| s == id) {
+            stack.push(id.to_string());
+            return Err(format!("mod dependency cycle: {}", stack.join(" -> ")));
+        }
+        let (info, path) = match modinfos.get(id) {
+            Some(x) => x,
+            None => {
+                let mut known: Vec<&String> = modinfos.keys().collect();
+                known.sort();
+                let required_by = match stack.last() {
+                    Some(parent) => format!(" (required by {:?})", parent),
+                    None => "".to_string(),
+                };
+                return Err(format!(
+                    "unknown mod {:?}{}; available mods: {:?}",
+                    id, required_by, known
+                ));
+            }
+        };
+        if info.core {
+            return Ok(());
+        }
+        if info.obsolete {
+            log::warn!("mod {:?} is marked obsolete", id);
+        }
+        stack.push(id.to_string());
+        for dep in &info.dependencies {
+            visit(dep, modinfos, order, stack)?;
+        }
+        stack.pop();
+        order.push(ModSource {
+            id: id.to_string(),
+            path: path.clone(),
+            base: false,
+        });
+        Ok(())
+    }
+
+    for id in wanted {
+        visit(id, &modinfos, &mut order, &mut vec![])?;
+    }
+    Ok(order)
+}
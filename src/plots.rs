@@ -2,12 +2,13 @@ use crate::db::Db;
 use crate::schema;
 
 use schema::Volume;
+use std::path::Path;
 
 fn total_storage(item: &schema::Armor) -> Volume {
     let mut total = Volume::default();
     for pocket in &item.pocket_data {
         if let schema::PocketData::Normal(pocket) = pocket {
-            total.ml += pocket.max_contains_volume.ml;
+            total += pocket.max_contains_volume;
         }
     }
     total
@@ -18,12 +19,12 @@ fn enc_at_full(item: &schema::CataItem) -> i32 {
     let mut base = if let Some(max) = armor.max_encumbrance {
         max
     } else {
-        armor.encumbrance + total_storage(armor).ml / 250
+        armor.encumbrance + (total_storage(armor).ml() / 250) as i32
     };
     if item.header.flags.contains(&"VARSIZE".to_string()) {
         base /= 2;
     }
-    return base ;
+    base
 }
 
 fn enc_at_empty(item: &schema::CataItem) -> i32 {
@@ -32,23 +33,25 @@ fn enc_at_empty(item: &schema::CataItem) -> i32 {
     if item.header.flags.contains(&"VARSIZE".to_string()) {
         base /= 2;
     }
-    return base;
+    base
 }
 
-fn plot_belts(belts: &[&schema::CataItem], input: &BeltsInput) {
-    // plot_stuffs( belts, "belts", 0f32..10f32, 0f32..7f32, true);
-    plot_stuffs(belts, "belts", false, input).unwrap();
+fn plot_belts(belts: &[&schema::CataItem], input: &BeltsInput, out: &Path) {
+    // plot_stuffs( belts, out, 0f32..10f32, 0f32..7f32, true);
+    plot_stuffs(belts, out, false, input).unwrap();
 }
 
 fn plot_stuffs(
     stuffs: &[&schema::CataItem],
-    out: &str,
+    out: &Path,
     extra_lines: bool,
-    input: &BeltsInput
+    input: &BeltsInput,
 ) -> Result<(), Box<dyn std::error::Error>> {
     use plotters::prelude::*;
-    let out = "out/".to_string() + out + ".svg";
-    let root = SVGBackend::new(&out, (800, 900)).into_drawing_area();
+    if let Some(dir) = out.parent() {
+        std::fs::create_dir_all(dir)?;
+    }
+    let root = SVGBackend::new(out, (800, 900)).into_drawing_area();
     root.fill(&WHITE)?;
 
     let (root_a, root_b) = root.split_vertically(600);
@@ -59,9 +62,9 @@ fn plot_stuffs(
     let mut max_enc = 0.1;
     for stuff in stuffs {
         let armor = stuff.as_armor();
-        let holds_l = total_storage(armor).ml as f32 / 1000.0;
+        let holds_l = total_storage(armor).liters() as f32;
         let e2 = enc_at_full(stuff) as f32;
-        let denc = (e2 as f32 - armor.encumbrance as f32) / holds_l;
+        let denc = (e2 - armor.encumbrance as f32) / holds_l;
         let enc_per_l_at_full = e2 / holds_l;
         if holds_l > max_volume {
             max_volume = holds_l
@@ -105,18 +108,16 @@ fn plot_stuffs(
     chart_b.configure_mesh().draw()?;
 
     for (i, stuff) in stuffs.iter().enumerate() {
-        
         // if belt.header.name.as_str() != "tool belt"{
         //     continue
         // }
         // aaaa ! They all get enc = volume/250ml!
         // let min_enc = belt.as_armor().encumbrance();
-        let name = stuff.header.name.as_str();
+        let name = stuff.header.name.translated();
         let armor = stuff.as_armor();
         let at_empty = enc_at_empty(stuff);
         let at_full = enc_at_full(stuff);
-        let holds_ml = total_storage(armor).ml;
-        let holds_l = holds_ml as f32 / 1000.0;
+        let holds_l = total_storage(armor).liters() as f32;
         let color = {
             // let mut s = std::collections::hash_map::DefaultHasher::new();
             // use std::hash::{Hash, Hasher};
@@ -126,7 +127,7 @@ fn plot_stuffs(
             Palette99::pick(i)
         };
         let enc_per_l_at_full = at_full as f32 / holds_l;
-        let enc_per_l_delta = (at_full - at_empty) as f32 / holds_l as f32;
+        let enc_per_l_delta = (at_full - at_empty) as f32 / holds_l;
         // dbg!(enc_per_l_delta);
 
         if extra_lines {
@@ -145,7 +146,7 @@ fn plot_stuffs(
                     EmptyElement::at(coord)
                         + Circle::new((0, 0), size, style)
                         + Text::new(
-                            if labels { format!("{}", name) } else { "".to_string() },
+                            if labels { name.to_string() } else { "".to_string() },
                             (-15, 15),
                             ("sans-serif", 15),
                         )
@@ -189,10 +190,9 @@ struct BeltsInput {
     labels: bool,
 }
 
-#[allow(dead_code)]
-pub(crate) fn belts(db: &Db) {
-    let input: BeltsInput =
-        json5::from_str(&std::fs::read_to_string("input/belts.json5").unwrap()).unwrap();
+pub(crate) fn belts(db: &Db, input_path: &Path, out_path: &Path) {
+    let input: BeltsInput = crate::read_input(input_path);
+    let whitelist = crate::resolve_input(db, input_path, "whitelist", &input.whitelist);
 
     let mut items = vec![];
     for item in &db.raw {
@@ -208,10 +208,10 @@ pub(crate) fn belts(db: &Db) {
     let mut belts = vec![];
     for item in &items {
         let armor = item.as_armor();
-        if total_storage(armor).ml == 0 {
+        if total_storage(armor) == Volume::default() {
             continue;
         }
-        if input.pocket_flag_any.len() > 0 {
+        if !input.pocket_flag_any.is_empty() {
             let has_matching_pockets = armor
                 .pocket_data
                 .iter()
@@ -220,41 +220,36 @@ pub(crate) fn belts(db: &Db) {
                 .any(|n: &schema::PocketNormal| {
                     n.flag_restriction
                         .iter()
-                        .any(|f| input.pocket_flag_any.contains(&f))
+                        .any(|f| input.pocket_flag_any.contains(f))
                 });
             if !has_matching_pockets {
                 continue;
             }
         }
-        if input.whitelist.len() > 0 {
-            if !input
-                .whitelist
-                .contains(&item.header.name.as_str().to_string())
-            {
-                // println!("hi? {}", item.header.name.as_str());
-                continue;
-            }
+        if !whitelist.is_empty() && !whitelist.contains(&item.header.id) {
+            // println!("hi? {}", item.header.name.as_str());
+            continue;
         }
-        if total_storage(armor).ml <= 1_000 {
+        if total_storage(armor) <= Volume::from_ml(1_000) {
             // continue;
         };
-        if enc_at_full(item) as f32 / (total_storage(armor).ml as f32 / 1000.0) > 0.8 {
+        if enc_at_full(item) as f32 / total_storage(armor).liters() as f32 > 0.8 {
             // continue;
         }
-        let holds_l = total_storage(armor).ml as f32 / 1000.0;
+        let holds_l = total_storage(armor).liters() as f32;
         let denc = (enc_at_full(item) - enc_at_empty(item)) as f32 / holds_l;
-        if enc_at_full(item) == enc_at_empty(item){
-            continue
+        if enc_at_full(item) == enc_at_empty(item) {
+            continue;
         }
-        if denc < 1.0 || denc >= 4.0 || holds_l > 10.0 {
+        if !(1.0..4.0).contains(&denc) || holds_l > 10.0 {
             continue;
         }
-        if !item.header.name.as_str().contains("survivor"){
+        if !item.header.name.as_str().contains("survivor") {
             // continue
         }
         // println!("{} {} {}", denc, enc_at_full(item), enc_at_empty(item));
         belts.push(item);
     }
 
-    plot_belts(&belts, &input);
+    plot_belts(&belts, &input, out_path);
 }
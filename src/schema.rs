@@ -6,28 +6,43 @@ pub(crate) enum Name {
     Plain(String),
     Same {
         str_sp: String,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        ctxt: Option<String>,
     },
     WithPlural {
         str: String,
-        #[allow(dead_code)]
         str_pl: Option<String>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        ctxt: Option<String>,
     },
 }
 impl Name {
-    pub(crate) fn to_string(self) -> String {
+    pub(crate) fn as_str(&self) -> &str {
         match self {
             Name::Plain(s) => s,
-            Name::Same { str_sp } => str_sp,
+            Name::Same { str_sp, .. } => str_sp,
             Name::WithPlural { str, .. } => str,
         }
     }
-    pub(crate) fn as_str(&self) -> &str {
+    // the game adds an "s" unless told otherwise
+    pub(crate) fn plural(&self) -> String {
         match self {
-            Name::Plain(s) => s,
-            Name::Same { str_sp } => str_sp,
-            Name::WithPlural { str, .. } => str,
+            Name::Plain(s) => format!("{}s", s),
+            Name::Same { str_sp, .. } => str_sp.clone(),
+            Name::WithPlural { str, str_pl, .. } => match str_pl {
+                Some(str_pl) => str_pl.clone(),
+                None => format!("{}s", str),
+            },
         }
     }
+    // in the language picked with --lang, English when there's no translation
+    pub(crate) fn translated(&self) -> &str {
+        let context = match self {
+            Name::Plain(_) => None,
+            Name::Same { ctxt, .. } | Name::WithPlural { ctxt, .. } => ctxt.as_deref(),
+        };
+        crate::translation::translate(context, self.as_str())
+    }
 }
 impl std::fmt::Debug for Name {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
@@ -54,108 +69,281 @@ pub(crate) struct Comestible {
     pub fun: i32,
 }
 
-#[derive(Clone, Debug, serde::Deserialize)]
-#[serde(from = "ComponentDescRaw")]
-pub(crate) enum ComponentDesc {
-    Plain(String, i32),
-    List(String, i32),
-    // ListRaw(String, i32, String),
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum ComponentKind {
+    Item,
+    // the id is a requirement, any of its components will do
+    List,
+}
+
+// One alternative of a component: ["id", amount], optionally followed by a flag
+// or a list of flags, "LIST" and/or "NO_RECOVER".
+// Serialize goes back to the game's shape so that cached recipes read back in.
+// Same for the other types that end up in the cached `Db`.
+#[derive(Clone, Debug, serde::Deserialize, serde::Serialize)]
+#[serde(try_from = "ComponentDescRaw", into = "ComponentDescRaw")]
+pub(crate) struct ComponentDesc {
+    pub id: String,
+    pub amount: i32,
+    pub kind: ComponentKind,
+    // NO_RECOVER ones are used up for good, disassembling doesn't give them back
+    pub recoverable: bool,
 }
 impl ComponentDesc {
-    pub fn name(&self) -> &str {
-        match self {
-            Self::Plain(s, _) => s,
-            Self::List(s, _) => s,
-        }
-    }
-    pub fn amount(&self) -> i32 {
-        match self {
-            Self::Plain(_, i) => *i,
-            Self::List(_, i) => *i,
+    // for tools the amount is the charges used, -1 for none
+    pub fn charges(&self) -> Option<i32> {
+        if self.amount > 0 {
+            Some(self.amount)
+        } else {
+            None
         }
     }
 }
 
-#[derive(Clone, Debug, serde::Deserialize)]
+#[derive(Clone, Debug, serde::Deserialize, serde::Serialize)]
 #[serde(untagged)]
 enum ComponentDescRaw {
     Two(String, i32),
     Three(String, i32, String),
     ThreeList(String, i32, Vec<String>),
 }
-impl From<ComponentDescRaw> for ComponentDesc {
-    fn from(f: ComponentDescRaw) -> ComponentDesc {
+impl std::convert::TryFrom<ComponentDescRaw> for ComponentDesc {
+    type Error = String;
+    fn try_from(f: ComponentDescRaw) -> Result<ComponentDesc, String> {
         use ComponentDescRaw::*;
-        match f {
-            Two(s, i) => ComponentDesc::Plain(s, i),
-            Three(s, i, which) if which == "LIST" => ComponentDesc::List(s, i),
-            Three(s, i, which) if which == "NO_RECOVER" => {
-                // TODO: NO_RECOVER is more special than this
-                ComponentDesc::List(s, i)
+        let (id, amount, flags) = match f {
+            Two(s, i) => (s, i, vec![]),
+            Three(s, i, flag) => (s, i, vec![flag]),
+            ThreeList(s, i, flags) => (s, i, flags),
+        };
+        let mut desc = ComponentDesc {
+            id,
+            amount,
+            kind: ComponentKind::Item,
+            recoverable: true,
+        };
+        for flag in flags {
+            match flag.as_str() {
+                "LIST" => desc.kind = ComponentKind::List,
+                "NO_RECOVER" => desc.recoverable = false,
+                _ => {
+                    return Err(format!(
+                        "unknown flag {:?} on component {:?}",
+                        flag, desc.id
+                    ))
+                }
             }
-            _ => unimplemented!("can't recognize {:?}", f),
         }
+        Ok(desc)
     }
 }
-#[derive(Clone, Debug, serde::Deserialize)]
+impl From<ComponentDesc> for ComponentDescRaw {
+    fn from(f: ComponentDesc) -> ComponentDescRaw {
+        let mut flags = vec![];
+        if f.kind == ComponentKind::List {
+            flags.push("LIST".to_string());
+        }
+        if !f.recoverable {
+            flags.push("NO_RECOVER".to_string());
+        }
+        match flags.len() {
+            0 => ComponentDescRaw::Two(f.id, f.amount),
+            1 => ComponentDescRaw::Three(f.id, f.amount, flags.remove(0)),
+            _ => ComponentDescRaw::ThreeList(f.id, f.amount, flags),
+        }
+    }
+}
+// Any number of "<number> <unit>", the space optional: "1 h 30 m", "90m", "1.5 L".
+// `units` are given in the smallest unit, which the total comes out in.
+fn parse_units(s: &str, units: &[(&str, i64)], what: &str) -> Result<i64, String> {
+    let mut rest = s.trim();
+    if rest.is_empty() {
+        return Err(format!("empty {}", what));
+    }
+    let mut total = 0;
+    while !rest.is_empty() {
+        let number_len = rest
+            .find(|c: char| !(c.is_ascii_digit() || c == '.' || c == '-'))
+            .unwrap_or(rest.len());
+        let number = &rest[..number_len];
+        rest = rest[number_len..].trim_start();
+        let unit_len = rest
+            .find(|c: char| !c.is_alphabetic())
+            .unwrap_or(rest.len());
+        let unit = &rest[..unit_len];
+        let size = units
+            .iter()
+            .find(|(known, _)| *known == unit)
+            .map(|(_, size)| *size)
+            .ok_or_else(|| format!("unknown unit {:?} in {} {:?}", unit, what, s))?;
+        total += parse_fixed(number, size).ok_or_else(|| format!("not a {}: {:?}", what, s))?;
+        rest = rest[unit_len..].trim_start();
+    }
+    Ok(total)
+}
+
+// A decimal `number` times `scale`, exact unless there are more decimals than that has room for,
+// in which case it's rounded to the nearest.
+fn parse_fixed(number: &str, scale: i64) -> Option<i64> {
+    use std::convert::TryFrom;
+    let (negative, digits) = match number.strip_prefix('-') {
+        Some(digits) => (true, digits),
+        None => (false, number),
+    };
+    let (whole, fraction) = digits.split_once('.').unwrap_or((digits, ""));
+    let all_digits = whole
+        .chars()
+        .chain(fraction.chars())
+        .all(|c| c.is_ascii_digit());
+    if !all_digits || (whole.is_empty() && fraction.is_empty()) {
+        return None;
+    }
+    let whole: i128 = if whole.is_empty() {
+        0
+    } else {
+        whole.parse().ok()?
+    };
+    let fraction = &fraction[..fraction.len().min(18)];
+    let denominator = 10i128.pow(fraction.len() as u32);
+    let numerator: i128 = if fraction.is_empty() {
+        0
+    } else {
+        fraction.parse().ok()?
+    };
+    let amount =
+        whole * scale as i128 + (numerator * scale as i128 + denominator / 2) / denominator;
+    let amount = i64::try_from(amount).ok()?;
+    Some(if negative { -amount } else { amount })
+}
+
+// A span of game time, kept in moves: a turn is a second is 100 moves.
+// The game writes these as "1 h 30 m", "2 days", "50 turns" and so on,
+// old recipe times are plain numbers of moves. Cached as moves.
+#[derive(
+    Clone,
+    Copy,
+    Debug,
+    Default,
+    PartialEq,
+    Eq,
+    PartialOrd,
+    Ord,
+    Hash,
+    serde::Deserialize,
+    serde::Serialize,
+)]
+#[serde(try_from = "DurationRaw", into = "DurationRaw")]
+pub(crate) struct Duration {
+    moves: i64,
+}
+
+#[derive(serde::Deserialize, serde::Serialize)]
 #[serde(untagged)]
-pub(crate) enum RecipeTime {
-    Human(String),
-    TicksMaybe(i32),
+enum DurationRaw {
+    Moves(i64),
+    Text(String),
 }
-impl Default for RecipeTime {
-    fn default() -> Self {
-        Self::TicksMaybe(0)
+
+const MOVES_PER_SECOND: i64 = 100;
+// what the game accepts, in moves
+const DURATION_UNITS: &[(&str, i64)] = &[
+    ("turns", 100),
+    ("turn", 100),
+    ("t", 100),
+    ("seconds", 100),
+    ("second", 100),
+    ("s", 100),
+    ("minutes", 60 * 100),
+    ("minute", 60 * 100),
+    ("m", 60 * 100),
+    ("hours", 3600 * 100),
+    ("hour", 3600 * 100),
+    ("h", 3600 * 100),
+    ("days", 86400 * 100),
+    ("day", 86400 * 100),
+    ("d", 86400 * 100),
+];
+
+impl Duration {
+    pub fn from_moves(moves: i64) -> Duration {
+        Duration { moves }
+    }
+    pub fn moves(self) -> i64 {
+        self.moves
+    }
+    // whole seconds, rounded down
+    pub fn seconds(self) -> i64 {
+        self.moves.div_euclid(MOVES_PER_SECOND)
     }
 }
-impl RecipeTime {
-    pub fn to_human(&self) -> String {
-        match self {
-            RecipeTime::Human(h) => h.to_string(),
-            RecipeTime::TicksMaybe(t) => format!(" ??? {} ticks ???", t),
+
+impl std::str::FromStr for Duration {
+    type Err = String;
+    fn from_str(s: &str) -> Result<Duration, String> {
+        parse_units(s, DURATION_UNITS, "duration").map(Duration::from_moves)
+    }
+}
+
+impl std::convert::TryFrom<DurationRaw> for Duration {
+    type Error = String;
+    fn try_from(raw: DurationRaw) -> Result<Duration, String> {
+        match raw {
+            DurationRaw::Moves(moves) => Ok(Duration::from_moves(moves)),
+            DurationRaw::Text(s) => s.parse(),
         }
     }
-    pub fn to_seconds(&self) -> i32 {
-        let s = self.to_human();
-        let mut res = 0i32;
-        let mut mult = 1;
-        for c in s.chars().rev() {
-            match c {
-                's' => mult = 1,
-                'm' => mult = 60,
-                'h' => mult = 60 * 60,
-                '0'..='9' => {
-                    let digit = c.to_digit(10).unwrap() as i32;
-                    res += digit * mult;
-                    mult *= 10;
-                }
-                ' ' => {}
-                _ => panic!("not a time thing {:?}", s),
+}
+
+impl From<Duration> for DurationRaw {
+    fn from(duration: Duration) -> DurationRaw {
+        DurationRaw::Moves(duration.moves)
+    }
+}
+
+// biggest units first, zeroes left out: "1 d 2 h", "20 m", "1 m 0.5 s"
+impl std::fmt::Display for Duration {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.moves < 0 {
+            return write!(f, "-{}", Duration::from_moves(-self.moves));
+        }
+        let mut parts = vec![];
+        let mut seconds = self.seconds();
+        for (unit, size) in [("d", 86400), ("h", 3600), ("m", 60)] {
+            if seconds >= size {
+                parts.push(format!("{} {}", seconds / size, unit));
+                seconds %= size;
             }
         }
-        res
+        let moves = self.moves % MOVES_PER_SECOND;
+        if moves != 0 {
+            let fraction = moves as f64 / MOVES_PER_SECOND as f64;
+            parts.push(format!("{} s", seconds as f64 + fraction));
+        } else if seconds != 0 || parts.is_empty() {
+            parts.push(format!("{} s", seconds));
+        }
+        write!(f, "{}", parts.join(" "))
     }
 }
-#[derive(Clone, Debug, serde::Deserialize)]
+
+#[derive(Clone, Debug, serde::Deserialize, serde::Serialize)]
 pub(crate) struct Quality {
     pub id: String,
+    #[serde(default = "default_quality_level")]
     pub level: i32,
 }
+fn default_quality_level() -> i32 {
+    1
+}
 
-#[derive(Clone, Debug, PartialEq, serde::Deserialize)]
+#[derive(Clone, Debug, Default, PartialEq, serde::Deserialize)]
 #[serde(untagged)]
 pub(crate) enum Autolearn {
+    #[default]
     No,
     Yes,
     Complex(Vec<(String, i32)>),
 }
 
-impl Default for Autolearn {
-    fn default() -> Self {
-        Autolearn::No
-    }
-}
-
 fn deserialize_autolearn<'de, D>(deserializer: D) -> Result<Autolearn, D::Error>
 where
     D: serde::Deserializer<'de>,
@@ -175,7 +363,65 @@ where
     })
 }
 
-#[derive(Clone, Debug, serde::Deserialize)]
+// A skill_used level, or levels of several skills
+#[derive(Clone, Debug, serde::Deserialize, serde::Serialize)]
+#[serde(untagged)]
+pub(crate) enum DecompLearn {
+    Level(i32),
+    Skills(Vec<(String, i32)>),
+}
+
+// [[book, level], [book, level, name shown]] or {book: {"skill_level": level, ...}}
+fn deserialize_book_learn<'de, D>(deserializer: D) -> Result<Vec<(String, i32)>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    #[derive(serde::Deserialize)]
+    #[serde(untagged)]
+    enum Taught {
+        Plain(String, i32),
+        // with the name shown in the recipe list, which doesn't matter here
+        Named(String, i32, #[allow(dead_code)] serde::de::IgnoredAny),
+    }
+    #[derive(serde::Deserialize)]
+    struct Described {
+        #[serde(default)]
+        skill_level: i32,
+    }
+    #[derive(serde::Deserialize)]
+    #[serde(untagged)]
+    enum BL {
+        List(Vec<Taught>),
+        Map(std::collections::BTreeMap<String, Described>),
+    }
+    use serde::Deserialize;
+    Ok(match BL::deserialize(deserializer)? {
+        BL::List(books) => books
+            .into_iter()
+            .map(|taught| match taught {
+                Taught::Plain(book, level) | Taught::Named(book, level, _) => (book, level),
+            })
+            .collect(),
+        BL::Map(books) => books
+            .into_iter()
+            .map(|(book, d)| (book, d.skill_level))
+            .collect(),
+    })
+}
+
+fn serialize_autolearn<S>(autolearn: &Autolearn, serializer: S) -> Result<S::Ok, S::Error>
+where
+    S: serde::Serializer,
+{
+    use serde::Serialize;
+    match autolearn {
+        Autolearn::No => false.serialize(serializer),
+        Autolearn::Yes => true.serialize(serializer),
+        Autolearn::Complex(v) => v.serialize(serializer),
+    }
+}
+
+#[derive(Clone, Debug, serde::Deserialize, serde::Serialize)]
 pub(crate) struct Recipe {
     pub result: String,
     pub id_suffix: Option<String>,
@@ -187,19 +433,72 @@ pub(crate) struct Recipe {
     #[serde(default)]
     pub difficulty: i32,
     #[serde(default)] // see "seed_oats"
-    pub time: RecipeTime,
+    pub time: Duration,
+    // [percent saved on each craft past the first few, how many those are]
+    #[serde(default)]
+    pub batch_time_factors: Option<(i32, i32)>,
     pub charges: Option<i32>,
     pub result_mult: Option<i32>, // default is 1, but eh
+    #[serde(default)] // everything may come from "using"
     pub components: Vec<Vec<ComponentDesc>>,
     #[serde(default)] // see "seed_oats"
     pub qualities: Vec<Quality>,
-    #[serde(deserialize_with = "deserialize_autolearn", default)]
+    #[serde(default)]
+    pub tools: Vec<Vec<ComponentDesc>>,
+    // whole requirements to include, and how many times
+    #[serde(default)]
+    pub using: Vec<(String, i32)>,
+    // Not in the game's data: every requirement inlined into it, by "using" or a LIST,
+    // and those they inline in turn
+    #[serde(default)]
+    pub inlined_requirements: Vec<String>,
+    #[serde(
+        deserialize_with = "deserialize_autolearn",
+        serialize_with = "serialize_autolearn",
+        default
+    )]
     pub autolearn: Autolearn,
+    // books teaching it, and the skill level needed to learn it from them
+    #[serde(default, deserialize_with = "deserialize_book_learn")]
+    pub book_learn: Vec<(String, i32)>,
+    // learnt by taking the result apart
+    pub decomp_learn: Option<DecompLearn>,
+    // missing ones make crafting slower and failing likelier, unless required outright
+    #[serde(default)]
+    pub proficiencies: Vec<RecipeProficiency>,
     #[serde(default)]
     pub reversible: bool,
 }
 
-#[derive(Clone, Debug, serde::Deserialize)]
+impl Recipe {
+    // Making `batch` at once, the way the game times it: the x-th one made (counting from 0)
+    // is `percent`% faster times a logistic curve that starts at 0 and is 99.5% of the way
+    // there by the x of the factors' batch size.
+    pub fn batch_time(&self, batch: i32) -> Duration {
+        let (percent, size) = match self.batch_time_factors {
+            Some(factors) => factors,
+            None => return self.time * batch as i64,
+        };
+        let rscale = percent as f64 / 100.0;
+        let mut total = 0.0;
+        for x in 0..batch {
+            let x = x as f64;
+            // a size of 0 gets there right after the first one
+            let logf = if size > 0 {
+                2.0 / (1.0 + (-x / (size as f64 / 6.0)).exp()) - 1.0
+            } else if x > 0.0 {
+                1.0
+            } else {
+                0.0
+            };
+            total += self.time.moves() as f64 * (1.0 - rscale * logf);
+        }
+        Duration::from_moves(total.round() as i64)
+    }
+}
+
+#[derive(Clone, Debug, serde::Deserialize, serde::Serialize)]
+#[allow(dead_code)]
 pub(crate) struct Material {
     #[serde(rename = "type")]
     pub typ: String,
@@ -219,80 +518,321 @@ pub(crate) struct Material {
 }
 
 #[derive(Clone, Debug, serde::Deserialize)]
+pub(crate) struct ModInfo {
+    pub id: String,
+    #[serde(default)]
+    pub dependencies: Vec<String>,
+    pub path: Option<String>, // relative to the modinfo.json
+    #[serde(default)]
+    pub core: bool,
+    #[serde(default)]
+    pub obsolete: bool,
+}
+
+#[derive(Clone, Debug, serde::Deserialize, serde::Serialize)]
 pub(crate) struct Requirement {
     pub id: String,
     #[serde(rename = "//")]
+    #[allow(dead_code)]
     pub comment: Option<String>,
     #[serde(default)] // sometimes we only have tools. See "22_casehead"
     pub components: Vec<Vec<ComponentDesc>>,
+    #[serde(default)]
+    pub tools: Vec<Vec<ComponentDesc>>,
+    #[serde(default)]
+    pub qualities: Vec<Quality>,
 }
 
-#[derive(Clone, Debug, Default, serde::Serialize, Copy)]
+#[derive(Clone, Debug, serde::Deserialize, serde::Serialize)]
+pub(crate) struct RecipeProficiency {
+    pub proficiency: String,
+    // can't be crafted at all without it
+    #[serde(default)]
+    pub required: bool,
+    // the proficiency's defaults if missing
+    pub time_multiplier: Option<f32>,
+    pub fail_multiplier: Option<f32>,
+    // how fast crafting this teaches it, compared to the time spent
+    #[serde(default = "default_learning_time_multiplier")]
+    pub learning_time_multiplier: f32,
+    // the most this recipe can teach of it
+    pub max_experience: Option<Duration>,
+}
+fn default_learning_time_multiplier() -> f32 {
+    1.0
+}
+
+#[derive(Clone, Debug, serde::Deserialize, serde::Serialize)]
+pub(crate) struct Proficiency {
+    pub id: String,
+    pub name: Name,
+    #[serde(default = "default_true")]
+    pub can_learn: bool,
+    #[serde(default = "default_proficiency_time_multiplier")]
+    pub default_time_multiplier: f32,
+    #[serde(default = "default_proficiency_fail_multiplier")]
+    pub default_fail_multiplier: f32,
+    // of practice
+    #[serde(default)]
+    pub time_to_learn: Duration,
+    // to know before any practice counts
+    #[serde(default)]
+    pub required_proficiencies: Vec<String>,
+}
+fn default_true() -> bool {
+    true
+}
+fn default_proficiency_time_multiplier() -> f32 {
+    2.0
+}
+fn default_proficiency_fail_multiplier() -> f32 {
+    2.5
+}
+
+// Exact amounts of space and mass, as the game keeps them: whole milliliters and milligrams.
+// Cached as text in those units.
+#[derive(
+    Clone,
+    Copy,
+    Debug,
+    Default,
+    PartialEq,
+    Eq,
+    PartialOrd,
+    Ord,
+    Hash,
+    serde::Deserialize,
+    serde::Serialize,
+)]
+#[serde(try_from = "QuantityRaw", into = "QuantityRaw")]
 pub(crate) struct Volume {
-    // s: String,
-    pub ml: i32,
-}
-
-impl<'de> serde::Deserialize<'de> for Volume {
-    fn deserialize<D>(deserializer: D) -> Result<Volume, D::Error>
-    where
-        D: serde::Deserializer<'de>,
-    {
-        let s = String::deserialize(deserializer)?;
-        let ml = if s.ends_with("ml") {
-            s[..s.len() - 2].trim().parse().unwrap()
-        } else if s.ends_with("L") {
-            s[..s.len() - 1].trim().parse::<i32>().unwrap() * 1000
-        } else {
-            panic!("{}", s)
-        };
-        Ok(Volume { ml })
-    }
+    ml: i64,
 }
 
-#[derive(Clone, Debug, Default, serde::Serialize, Copy)]
+#[derive(
+    Clone,
+    Copy,
+    Debug,
+    Default,
+    PartialEq,
+    Eq,
+    PartialOrd,
+    Ord,
+    Hash,
+    serde::Deserialize,
+    serde::Serialize,
+)]
+#[serde(try_from = "QuantityRaw", into = "QuantityRaw")]
 pub(crate) struct Weight {
-    // s: String,
-    pub g: i32,
-}
-
-impl<'de> serde::Deserialize<'de> for Weight {
-    fn deserialize<D>(deserializer: D) -> Result<Weight, D::Error>
-    where
-        D: serde::Deserializer<'de>,
-    {
-        use serde::de::Error;
-        let s = String::deserialize(deserializer)?;
-        let g = if s.ends_with(" kg") {
-            s[..s.len() - 3]
-                .trim()
-                .parse::<i32>()
-                .map_err(|e| D::Error::custom(e.to_string()))?
-                * 1000
-        } else if s.ends_with(" mg") {
-            // TODO: losing precision
-            s[..s.len() - 2]
-                .trim()
-                .parse::<i32>()
-                .map_err(|e| D::Error::custom(e.to_string()))?
-                / 1000
-        } else if s.ends_with(" g") {
-            // TODO: losing precision
-            s[..s.len() - 1]
-                .trim()
-                .parse::<i32>()
-                .map_err(|e| D::Error::custom(e.to_string()))?
-        } else {
-            panic!("{}", s)
-        };
-        Ok(Weight { g })
+    mg: i64,
+}
+
+// Only ever read as text, for copy-from: a length like "30 cm", an energy like "5 kJ".
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub(crate) struct Length {
+    mm: i64,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub(crate) struct Energy {
+    mj: i64,
+}
+
+#[derive(serde::Deserialize, serde::Serialize)]
+#[serde(untagged)]
+enum QuantityRaw {
+    // in the game's older units, 250 ml or a gram
+    Legacy(i64),
+    Text(String),
+}
+
+const VOLUME_UNITS: &[(&str, i64)] = &[("ml", 1), ("L", 1000)];
+const WEIGHT_UNITS: &[(&str, i64)] = &[("mg", 1), ("g", 1000), ("kg", 1_000_000)];
+const LENGTH_UNITS: &[(&str, i64)] = &[("mm", 1), ("cm", 10), ("m", 1000), ("km", 1_000_000)];
+const ENERGY_UNITS: &[(&str, i64)] = &[("mJ", 1), ("J", 1000), ("kJ", 1_000_000)];
+const LEGACY_VOLUME_ML: i64 = 250;
+
+impl Volume {
+    pub fn from_ml(ml: i64) -> Volume {
+        Volume { ml }
+    }
+    pub fn ml(self) -> i64 {
+        self.ml
+    }
+    pub fn liters(self) -> f64 {
+        self.ml as f64 / 1000.0
     }
 }
 
+impl Weight {
+    pub fn from_mg(mg: i64) -> Weight {
+        Weight { mg }
+    }
+    pub fn grams(self) -> f64 {
+        self.mg as f64 / 1000.0
+    }
+}
+
+impl std::str::FromStr for Volume {
+    type Err = String;
+    fn from_str(s: &str) -> Result<Volume, String> {
+        parse_units(s, VOLUME_UNITS, "volume").map(Volume::from_ml)
+    }
+}
+
+impl std::str::FromStr for Weight {
+    type Err = String;
+    fn from_str(s: &str) -> Result<Weight, String> {
+        parse_units(s, WEIGHT_UNITS, "weight").map(Weight::from_mg)
+    }
+}
+
+impl std::str::FromStr for Length {
+    type Err = String;
+    fn from_str(s: &str) -> Result<Length, String> {
+        parse_units(s, LENGTH_UNITS, "length").map(|mm| Length { mm })
+    }
+}
+
+impl std::str::FromStr for Energy {
+    type Err = String;
+    fn from_str(s: &str) -> Result<Energy, String> {
+        parse_units(s, ENERGY_UNITS, "energy").map(|mj| Energy { mj })
+    }
+}
+
+impl std::convert::TryFrom<QuantityRaw> for Volume {
+    type Error = String;
+    fn try_from(raw: QuantityRaw) -> Result<Volume, String> {
+        match raw {
+            QuantityRaw::Legacy(n) => Ok(Volume::from_ml(n * LEGACY_VOLUME_ML)),
+            QuantityRaw::Text(s) => s.parse(),
+        }
+    }
+}
+
+impl std::convert::TryFrom<QuantityRaw> for Weight {
+    type Error = String;
+    fn try_from(raw: QuantityRaw) -> Result<Weight, String> {
+        match raw {
+            QuantityRaw::Legacy(g) => Ok(Weight::from_mg(g * 1000)),
+            QuantityRaw::Text(s) => s.parse(),
+        }
+    }
+}
+
+impl From<Volume> for QuantityRaw {
+    fn from(volume: Volume) -> QuantityRaw {
+        QuantityRaw::Text(format!("{} ml", volume.ml))
+    }
+}
+
+impl From<Weight> for QuantityRaw {
+    fn from(weight: Weight) -> QuantityRaw {
+        QuantityRaw::Text(format!("{} mg", weight.mg))
+    }
+}
+
+// `amount` in the biggest of `units` it makes at least one of: "1.5 L", "250 ml"
+fn format_units(amount: i64, units: &[(&str, i64)]) -> String {
+    let (unit, size) = units
+        .iter()
+        .rev()
+        .find(|(_, size)| amount.abs() >= *size)
+        .unwrap_or(&units[0]);
+    format!("{} {}", amount as f64 / *size as f64, unit)
+}
+
+impl std::fmt::Display for Volume {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", format_units(self.ml, VOLUME_UNITS))
+    }
+}
+
+impl std::fmt::Display for Weight {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", format_units(self.mg, WEIGHT_UNITS))
+    }
+}
+
+impl std::fmt::Display for Length {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", format_units(self.mm, LENGTH_UNITS))
+    }
+}
+
+impl std::fmt::Display for Energy {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", format_units(self.mj, ENERGY_UNITS))
+    }
+}
+
+// the same arithmetic for all of them, and for durations
+macro_rules! impl_quantity_ops {
+    ($ty:ident, $field:ident) => {
+        impl $ty {
+            // rounded to the nearest of the smallest unit
+            pub fn scaled(self, factor: f64) -> $ty {
+                $ty {
+                    $field: (self.$field as f64 * factor).round() as i64,
+                }
+            }
+        }
+        impl std::ops::Add for $ty {
+            type Output = $ty;
+            fn add(self, other: $ty) -> $ty {
+                $ty {
+                    $field: self.$field + other.$field,
+                }
+            }
+        }
+        impl std::ops::AddAssign for $ty {
+            fn add_assign(&mut self, other: $ty) {
+                self.$field += other.$field;
+            }
+        }
+        impl std::ops::Sub for $ty {
+            type Output = $ty;
+            fn sub(self, other: $ty) -> $ty {
+                $ty {
+                    $field: self.$field - other.$field,
+                }
+            }
+        }
+        impl std::ops::Mul<i64> for $ty {
+            type Output = $ty;
+            fn mul(self, times: i64) -> $ty {
+                $ty {
+                    $field: self.$field * times,
+                }
+            }
+        }
+        impl std::ops::Div<i64> for $ty {
+            type Output = $ty;
+            fn div(self, parts: i64) -> $ty {
+                $ty {
+                    $field: self.$field / parts,
+                }
+            }
+        }
+        impl std::iter::Sum for $ty {
+            fn sum<I: Iterator<Item = $ty>>(iter: I) -> $ty {
+                iter.fold($ty::default(), |a, b| a + b)
+            }
+        }
+    };
+}
+impl_quantity_ops!(Duration, moves);
+impl_quantity_ops!(Volume, ml);
+impl_quantity_ops!(Weight, mg);
+impl_quantity_ops!(Length, mm);
+impl_quantity_ops!(Energy, mj);
+
 #[derive(Clone, Debug, serde::Deserialize)]
+#[allow(dead_code)]
 pub(crate) struct PocketNormal {
     pub max_contains_volume: Volume,
-    pub max_contains_weight: String,
+    pub max_contains_weight: Weight,
     #[serde(default)] // drinking_hat
     pub moves: i32,
     #[serde(default)]
@@ -300,6 +840,7 @@ pub(crate) struct PocketNormal {
 }
 
 #[derive(Clone, Debug, serde::Deserialize)]
+#[allow(dead_code)]
 pub(crate) struct PocketAmmo {
     ammo_restriction: Map<String, i32>,
 }
@@ -308,6 +849,7 @@ pub(crate) struct PocketAmmo {
 #[serde(untagged)]
 pub(crate) enum PocketData {
     Normal(PocketNormal),
+    #[allow(dead_code)]
     Ammo(PocketAmmo),
 }
 
@@ -346,6 +888,251 @@ where
     }
 }
 
+// One kind of damage, e.g. 20 "bullet" with 4 armor penetration.
+#[derive(Clone, Debug, Default, PartialEq, serde::Deserialize, serde::Serialize)]
+#[serde(default)]
+pub(crate) struct DamageUnit {
+    pub damage_type: String,
+    pub amount: f32,
+    pub armor_penetration: f32,
+}
+
+// All the kinds of damage something deals at once.
+// The game writes it as a number (old items), a single unit, {"values": [units]} or [units].
+#[derive(Clone, Debug, Default, PartialEq, serde::Deserialize, serde::Serialize)]
+#[serde(from = "DamageRaw", into = "DamageRaw")]
+pub(crate) struct Damage {
+    pub units: Vec<DamageUnit>,
+}
+
+#[derive(serde::Deserialize, serde::Serialize)]
+#[serde(untagged)]
+enum DamageRaw {
+    Plain(f32),
+    Units(Vec<DamageUnit>),
+    Values { values: Vec<DamageUnit> },
+    Unit(DamageUnit),
+}
+
+impl From<DamageRaw> for Damage {
+    fn from(raw: DamageRaw) -> Damage {
+        let units = match raw {
+            DamageRaw::Plain(amount) => vec![DamageUnit {
+                damage_type: "bullet".to_string(),
+                amount,
+                armor_penetration: 0.0,
+            }],
+            DamageRaw::Units(units) | DamageRaw::Values { values: units } => units,
+            DamageRaw::Unit(unit) => vec![unit],
+        };
+        Damage { units }
+    }
+}
+
+impl Damage {
+    pub fn total(&self) -> f32 {
+        self.units.iter().map(|u| u.amount).sum()
+    }
+    pub fn armor_penetration(&self) -> f32 {
+        self.units.iter().map(|u| u.armor_penetration).sum()
+    }
+}
+
+impl From<Damage> for DamageRaw {
+    fn from(damage: Damage) -> DamageRaw {
+        DamageRaw::Units(damage.units)
+    }
+}
+
+// A way of firing, e.g. ["AUTO", "auto", 3, ["NPC_AVOID"]].
+#[derive(Clone, Debug, PartialEq, serde::Deserialize, serde::Serialize)]
+#[serde(from = "GunModeRaw", into = "GunModeRaw")]
+pub(crate) struct GunMode {
+    pub id: String,
+    pub name: String,
+    pub shots: i32,
+    pub flags: Vec<String>,
+}
+
+#[derive(serde::Deserialize, serde::Serialize)]
+#[serde(untagged)]
+enum GunModeRaw {
+    Flagged(
+        String,
+        String,
+        i32,
+        #[serde(deserialize_with = "deserialize_vec_or_one")] Vec<String>,
+    ),
+    Plain(String, String, i32),
+}
+
+impl From<GunModeRaw> for GunMode {
+    fn from(raw: GunModeRaw) -> GunMode {
+        let (id, name, shots, flags) = match raw {
+            GunModeRaw::Flagged(id, name, shots, flags) => (id, name, shots, flags),
+            GunModeRaw::Plain(id, name, shots) => (id, name, shots, vec![]),
+        };
+        GunMode {
+            id,
+            name,
+            shots,
+            flags,
+        }
+    }
+}
+
+impl From<GunMode> for GunModeRaw {
+    fn from(mode: GunMode) -> GunModeRaw {
+        GunModeRaw::Flagged(mode.id, mode.name, mode.shots, mode.flags)
+    }
+}
+
+// The part of a pocket guns and magazines care about: what ammo or magazines go in.
+#[derive(Clone, Debug, Default, serde::Deserialize, serde::Serialize)]
+#[serde(default)]
+pub(crate) struct AmmoPocket {
+    pub pocket_type: Option<String>, // CONTAINER if not given
+    pub ammo_restriction: Map<String, i32>,
+    pub item_restriction: Vec<String>,
+}
+
+fn default_reload() -> Duration {
+    Duration::from_moves(100)
+}
+
+#[derive(Clone, Debug, Default, serde::Deserialize, serde::Serialize)]
+#[serde(default)]
+#[allow(dead_code)]
+pub(crate) struct Gun {
+    pub skill: String,
+    // ammo types it fires
+    #[serde(deserialize_with = "deserialize_vec_or_one")]
+    pub ammo: Vec<String>,
+    pub ranged_damage: Damage,
+    pub range: i32,
+    pub dispersion: i32,
+    pub sight_dispersion: i32,
+    pub recoil: i32,
+    pub durability: i32,
+    pub loudness: i32,
+    #[serde(default = "default_reload")]
+    pub reload: Duration,
+    // none means the one single shot mode
+    pub modes: Vec<GunMode>,
+    pub ammo_effects: Vec<String>,
+    // old style magazine compatibility, [[ammo type, [magazine ids]]]
+    pub magazines: Vec<(String, Vec<String>)>,
+    pub pocket_data: Vec<AmmoPocket>,
+}
+
+impl Gun {
+    // magazine ids it takes, from MAGAZINE_WELL pockets or the old "magazines" list
+    pub fn compatible_magazines(&self) -> Vec<&str> {
+        let wells = self
+            .pocket_data
+            .iter()
+            .filter(|p| p.pocket_type.as_deref() == Some("MAGAZINE_WELL"))
+            .flat_map(|p| &p.item_restriction);
+        let listed = self.magazines.iter().flat_map(|(_, ids)| ids);
+        let mut ids: Vec<&str> = wells.chain(listed).map(|id| id.as_str()).collect();
+        ids.sort_unstable();
+        ids.dedup();
+        ids
+    }
+}
+
+#[derive(Clone, Debug, Default, serde::Deserialize, serde::Serialize)]
+#[serde(default)]
+#[allow(dead_code)]
+pub(crate) struct Ammo {
+    pub ammo_type: String,
+    pub damage: Damage,
+    pub range: i32,
+    pub dispersion: i32,
+    pub recoil: i32,
+    pub count: Option<i32>,
+    pub loudness: Option<i32>,
+    pub casing: Option<String>,
+    pub effects: Vec<String>,
+}
+
+#[derive(Clone, Debug, Default, serde::Deserialize, serde::Serialize)]
+#[serde(default)]
+#[allow(dead_code)]
+pub(crate) struct Magazine {
+    #[serde(deserialize_with = "deserialize_vec_or_one")]
+    pub ammo_type: Vec<String>,
+    // old style, before pockets
+    pub capacity: Option<i32>,
+    // per round put in
+    #[serde(default = "default_reload")]
+    pub reload_time: Duration,
+    pub default_ammo: Option<String>,
+    pub linkage: Option<String>,
+    pub pocket_data: Vec<AmmoPocket>,
+}
+
+impl Magazine {
+    // rounds of `ammo_type` it holds
+    pub fn capacity_for(&self, ammo_type: &str) -> i32 {
+        let pockets = self
+            .pocket_data
+            .iter()
+            .filter(|p| matches!(p.pocket_type.as_deref(), None | Some("MAGAZINE")));
+        let in_pockets: i32 = pockets
+            .filter_map(|p| p.ammo_restriction.get(ammo_type))
+            .sum();
+        if in_pockets > 0 {
+            return in_pockets;
+        }
+        match self.capacity {
+            Some(capacity) if self.ammo_type.iter().any(|t| t == ammo_type) => capacity,
+            _ => 0,
+        }
+    }
+}
+
+// Legacy book times are plain minutes rather than moves.
+fn deserialize_book_time<'de, D>(deserializer: D) -> Result<Duration, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    use serde::Deserialize;
+    Ok(match DurationRaw::deserialize(deserializer)? {
+        DurationRaw::Moves(minutes) => Duration::from_moves(minutes * 60 * MOVES_PER_SECOND),
+        DurationRaw::Text(s) => s.parse().map_err(serde::de::Error::custom)?,
+    })
+}
+
+fn serialize_book_time<S>(time: &Duration, serializer: S) -> Result<S::Ok, S::Error>
+where
+    S: serde::Serializer,
+{
+    serializer.collect_str(time)
+}
+
+#[derive(Clone, Debug, Default, serde::Deserialize, serde::Serialize)]
+#[serde(default)]
+#[allow(dead_code)]
+pub(crate) struct Book {
+    // what it trains, if anything
+    pub skill: Option<String>,
+    // needed to learn from it
+    pub required_level: i32,
+    // as far as it trains
+    pub max_level: i32,
+    // reading is slower below this
+    pub intelligence: i32,
+    // of one reading session
+    #[serde(
+        deserialize_with = "deserialize_book_time",
+        serialize_with = "serialize_book_time"
+    )]
+    pub time: Duration,
+    pub chapters: i32,
+    pub fun: i32,
+}
+
 #[derive(Clone, Debug, serde::Deserialize, serde::Serialize)]
 struct Generic {}
 #[derive(Clone, Debug, serde::Deserialize, serde::Serialize)]
@@ -362,6 +1149,14 @@ enum GenericItemEnum {
     Generic(Generic),
     #[serde(rename = "TOOL")]
     Tool(Tool),
+    #[serde(rename = "GUN")]
+    Gun(Gun),
+    #[serde(rename = "AMMO")]
+    Ammo(Ammo),
+    #[serde(rename = "MAGAZINE")]
+    Magazine(Magazine),
+    #[serde(rename = "BOOK")]
+    Book(Book),
 }
 #[derive(Clone, Debug, serde::Deserialize, serde::Serialize)]
 pub(crate) struct GenericItemHeader {
@@ -378,6 +1173,57 @@ pub(crate) struct GenericItemHeader {
     pub cutting: i32,
     #[serde(default)]
     pub bashing: i32,
+    // newer items give melee damage by type instead, {"bash": 8, "stab": 12}
+    #[serde(default)]
+    pub melee_damage: Map<String, f32>,
+    #[serde(default)]
+    pub to_hit: ToHit,
+    #[serde(default)]
+    pub techniques: Vec<String>,
+    // what martial arts can use it, besides listing it by id
+    #[serde(default)]
+    pub weapon_category: Vec<String>,
+}
+
+// Melee accuracy bonus, either a number or described by how it's held and what it hits with.
+#[derive(Clone, Debug, serde::Deserialize, serde::Serialize)]
+#[serde(untagged)]
+pub(crate) enum ToHit {
+    Plain(i32),
+    Described {
+        grip: String,
+        length: String,
+        surface: String,
+        balance: String,
+    },
+}
+
+impl Default for ToHit {
+    fn default() -> Self {
+        Self::Plain(0)
+    }
+}
+
+impl ToHit {
+    pub fn value(&self) -> i32 {
+        let (grip, length, surface, balance) = match self {
+            ToHit::Plain(to_hit) => return *to_hit,
+            ToHit::Described {
+                grip,
+                length,
+                surface,
+                balance,
+            } => (grip, length, surface, balance),
+        };
+        let rank = |value: &str, ranks: &[&str]| {
+            ranks.iter().position(|r| *r == value).unwrap_or(0) as i32
+        };
+        // the way the game adds them up
+        -7 + rank(grip, &["bad", "none", "solid", "weapon"])
+            + rank(length, &["hand", "short", "long"])
+            + rank(surface, &["point", "line", "any", "every"])
+            + rank(balance, &["clumsy", "uneven", "neutral", "good"])
+    }
 }
 
 #[derive(serde::Deserialize)]
@@ -388,24 +1234,26 @@ struct GenericItemRaw {
     inner: serde_json::Value,
 }
 
-impl From<GenericItemRaw> for CataItem {
-    fn from(mut raw: GenericItemRaw) -> CataItem {
-        raw.inner.as_object_mut().unwrap().insert(
-            "type".to_string(),
-            serde_json::to_value(&raw.header.typ).unwrap(),
-        );
+impl std::convert::TryFrom<GenericItemRaw> for CataItem {
+    type Error = String;
+    fn try_from(mut raw: GenericItemRaw) -> Result<CataItem, String> {
+        match raw.inner.as_object_mut() {
+            Some(inner) => inner.insert(
+                "type".to_string(),
+                serde_json::Value::String(raw.header.typ.clone()),
+            ),
+            None => return Err(format!("an item must be an object, not {}", raw.inner)),
+        };
         // println!("{}", serde_json::to_string_pretty(&raw.inner).unwrap());
-        let inner: GenericItemEnum = serde_json::from_value(raw.inner.clone())
-            .unwrap_or_else(|e| panic!("{}: {:#?}", e, raw.inner));
-        CataItem {
-            header: raw.header,
-            inner,
-        }
+        let header = raw.header;
+        let inner: GenericItemEnum = serde_json::from_value(raw.inner)
+            .map_err(|e| format!("{} item {:?}: {}", header.typ, header.id, e))?;
+        Ok(CataItem { header, inner })
     }
 }
 
 #[derive(Clone, Debug, serde::Deserialize, serde::Serialize)]
-#[serde(from = "GenericItemRaw")]
+#[serde(try_from = "GenericItemRaw")]
 pub(crate) struct CataItem {
     pub header: GenericItemHeader,
     inner: GenericItemEnum,
@@ -413,6 +1261,7 @@ pub(crate) struct CataItem {
 macro_rules! cataitem_as {
     ($accessor_name:ident, $enum_variant:tt, $typ:ty) => {
         impl CataItem {
+            #[allow(dead_code)] // not every kind is looked at yet
             pub(crate) fn $accessor_name(&self) -> &$typ {
                 use GenericItemEnum::*;
                 match &self.inner {
@@ -437,7 +1286,25 @@ macro_rules! cataitem_as_mut {
         }
     };
 }
+// like `cataitem_as`, for items that might turn out to be of another kind
+macro_rules! cataitem_try_as {
+    ($accessor_name:ident, $enum_variant:tt, $typ:ty) => {
+        impl CataItem {
+            pub(crate) fn $accessor_name(&self) -> Option<&$typ> {
+                match &self.inner {
+                    GenericItemEnum::$enum_variant(inner) => Some(inner),
+                    _ => None,
+                }
+            }
+        }
+    };
+}
 cataitem_as!(as_armor, Armor, Armor);
+cataitem_as!(as_gun, Gun, Gun);
+cataitem_as!(as_ammo, Ammo, Ammo);
+cataitem_as!(as_magazine, Magazine, Magazine);
+cataitem_as!(as_book, Book, Book);
+cataitem_try_as!(try_as_magazine, Magazine, Magazine);
 
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub(crate) enum ItemGroupItemType {
@@ -451,12 +1318,14 @@ pub(crate) struct ItemGroupItem {
     pub id: String,
 }
 
-#[derive(Debug, Clone, serde::Deserialize)]
+#[derive(Debug, Clone, serde::Deserialize, serde::Serialize)]
+#[allow(dead_code)]
 pub(crate) struct ItemGroup {
     pub id: String,
     pub subtype: Option<String>,
     #[serde(alias = "entries")] // not actually alias but whatever
     #[serde(deserialize_with = "deserialize_items_in_itemgroup")]
+    #[serde(serialize_with = "serialize_items_in_itemgroup")]
     #[serde(default)]
     pub items: Vec<(ItemGroupItem, i32)>,
 }
@@ -493,8 +1362,12 @@ where
     #[serde(untagged)]
     enum ItemOrGroup {
         Item(String, Option<i32>),
-        Group { group: String, prob: Option<i32> },
+        Group {
+            group: String,
+            prob: Option<i32>,
+        },
         ItemVerbose(ItemVerbose),
+        #[allow(dead_code)]
         Scary(Scary),
     }
     use serde::Deserialize;
@@ -524,3 +1397,350 @@ where
         .collect();
     Ok(out)
 }
+
+fn serialize_items_in_itemgroup<S>(
+    items: &[(ItemGroupItem, i32)],
+    serializer: S,
+) -> Result<S::Ok, S::Error>
+where
+    S: serde::Serializer,
+{
+    use serde::ser::SerializeSeq;
+    #[derive(serde::Serialize)]
+    struct GroupOut<'a> {
+        group: &'a str,
+        prob: i32,
+    }
+    let mut seq = serializer.serialize_seq(Some(items.len()))?;
+    for (item, prob) in items {
+        match item.typ {
+            ItemGroupItemType::Item => seq.serialize_element(&(&item.id, prob))?,
+            ItemGroupItemType::Group => seq.serialize_element(&GroupOut {
+                group: &item.id,
+                prob: *prob,
+            })?,
+        }
+    }
+    seq.end()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn duration(s: &str) -> Duration {
+        s.parse().unwrap()
+    }
+
+    #[test]
+    fn durations() {
+        assert_eq!(duration("1 h 30 m"), duration("90 m"));
+        assert_eq!(duration("1h30m"), duration("5400 s"));
+        assert_eq!(duration("2 days"), duration("48 h"));
+        assert_eq!(duration("50 turns"), duration("50 s"));
+        assert_eq!(duration("1.5 hours"), duration("90 minutes"));
+        assert!("1 fortnight".parse::<Duration>().is_err());
+        assert!("".parse::<Duration>().is_err());
+        assert!("h".parse::<Duration>().is_err());
+    }
+
+    #[test]
+    fn durations_in_moves() {
+        let legacy: Duration = serde_json::from_value(serde_json::json!(6000)).unwrap();
+        assert_eq!(legacy, duration("1 m"));
+        let cached = serde_json::to_value(duration("1 m")).unwrap();
+        assert_eq!(serde_json::from_value::<Duration>(cached).unwrap(), legacy);
+    }
+
+    #[test]
+    fn durations_printed() {
+        assert_eq!(duration("1 d 2 h 0 m 5 s").to_string(), "1 d 2 h 5 s");
+        assert_eq!(duration("20 m").to_string(), "20 m");
+        assert_eq!(Duration::default().to_string(), "0 s");
+        assert_eq!(Duration::from_moves(150).to_string(), "1.5 s");
+        assert_eq!((duration("1 h") - duration("2 h")).to_string(), "-1 h");
+    }
+
+    fn volume(s: &str) -> Volume {
+        s.parse().unwrap()
+    }
+
+    fn weight(s: &str) -> Weight {
+        s.parse().unwrap()
+    }
+
+    #[test]
+    fn volumes() {
+        assert_eq!(volume("250 ml"), Volume::from_ml(250));
+        assert_eq!(volume("250ml"), Volume::from_ml(250));
+        assert_eq!(volume("2 L"), Volume::from_ml(2000));
+        assert_eq!(volume("1.5 L"), Volume::from_ml(1500));
+        assert_eq!(volume(".25 L"), Volume::from_ml(250));
+        assert_eq!(volume("1 L 500 ml"), Volume::from_ml(1500));
+        assert!("1.5 gallons".parse::<Volume>().is_err());
+        assert!("lots".parse::<Volume>().is_err());
+    }
+
+    #[test]
+    fn legacy_volumes() {
+        let legacy: Volume = serde_json::from_value(serde_json::json!(3)).unwrap();
+        assert_eq!(legacy, volume("750 ml"));
+        let text: Volume = serde_json::from_value(serde_json::json!("1.5 L")).unwrap();
+        assert_eq!(text, volume("1500 ml"));
+    }
+
+    #[test]
+    fn weights() {
+        assert_eq!(weight("5 mg"), Weight::from_mg(5));
+        assert_eq!(weight("100 g"), Weight::from_mg(100_000));
+        assert_eq!(weight("100g"), Weight::from_mg(100_000));
+        assert_eq!(weight("1.25 kg"), Weight::from_mg(1_250_000));
+        assert_eq!(weight("0.5 g"), Weight::from_mg(500));
+        // rounded to the nearest milligram
+        assert_eq!(weight("0.0004 g"), Weight::default());
+        assert!("3 stone".parse::<Weight>().is_err());
+    }
+
+    #[test]
+    fn legacy_weights() {
+        let legacy: Weight = serde_json::from_value(serde_json::json!(40)).unwrap();
+        assert_eq!(legacy, weight("40 g"));
+    }
+
+    #[test]
+    fn quantities_cached() {
+        for v in ["1.5 L", "250 ml", "0 ml"] {
+            let cached = serde_json::to_value(volume(v)).unwrap();
+            assert_eq!(serde_json::from_value::<Volume>(cached).unwrap(), volume(v));
+        }
+        for w in ["1.25 kg", "7 mg"] {
+            let cached = serde_json::to_value(weight(w)).unwrap();
+            assert_eq!(serde_json::from_value::<Weight>(cached).unwrap(), weight(w));
+        }
+    }
+
+    #[test]
+    fn quantity_arithmetic() {
+        assert_eq!(volume("1 L") + volume("500 ml"), volume("1.5 L"));
+        assert_eq!(volume("1 L") - volume("250 ml"), volume("750 ml"));
+        assert_eq!(weight("250 g") * 4, weight("1 kg"));
+        assert_eq!(weight("1 kg") / 4, weight("250 g"));
+        let total: Weight = ["1 g", "2 mg"].iter().map(|w| weight(w)).sum();
+        assert_eq!(total, weight("1002 mg"));
+        assert!(volume("999 ml") < volume("1 L"));
+        assert!(weight("1.5 kg") > weight("1499 g"));
+    }
+
+    #[test]
+    fn quantities_printed() {
+        assert_eq!(volume("1500 ml").to_string(), "1.5 L");
+        assert_eq!(volume("250 ml").to_string(), "250 ml");
+        assert_eq!(weight("2000 g").to_string(), "2 kg");
+        assert_eq!(weight("500 mg").to_string(), "500 mg");
+        assert_eq!(Volume::default().to_string(), "0 ml");
+    }
+
+    fn item(json: serde_json::Value) -> CataItem {
+        serde_json::from_value(json).unwrap()
+    }
+
+    #[test]
+    fn guns() {
+        let glock = item(serde_json::json!({
+            "type": "GUN",
+            "id": "glock_19",
+            "name": { "str": "Glock 19" },
+            "skill": "pistol",
+            "ammo": [ "9mm" ],
+            "ranged_damage": { "damage_type": "bullet", "amount": 1, "armor_penetration": 2 },
+            "dispersion": 480,
+            "sight_dispersion": 60,
+            "recoil": 300,
+            "modes": [ [ "DEFAULT", "semi-auto", 1 ], [ "BURST", "burst", 3, "NPC_AVOID" ] ],
+            "pocket_data": [
+                { "pocket_type": "MAGAZINE_WELL", "item_restriction": [ "glockmag", "glock_drum" ] }
+            ]
+        }));
+        let gun = glock.as_gun();
+        assert_eq!(gun.ammo, vec!["9mm"]);
+        assert_eq!(gun.ranged_damage.units[0].armor_penetration, 2.0);
+        assert_eq!(gun.reload, Duration::from_moves(100));
+        assert_eq!(gun.modes[1].shots, 3);
+        assert_eq!(gun.modes[1].flags, vec!["NPC_AVOID"]);
+        assert_eq!(gun.compatible_magazines(), vec!["glock_drum", "glockmag"]);
+
+        let old = item(serde_json::json!({
+            "type": "GUN",
+            "id": "old_rifle",
+            "name": "old rifle",
+            "ammo": "223",
+            "ranged_damage": 4,
+            "reload": 200,
+            "magazines": [ [ "223", [ "stanag30" ] ] ]
+        }));
+        let gun = old.as_gun();
+        assert_eq!(gun.ranged_damage.units[0].amount, 4.0);
+        assert_eq!(gun.reload, Duration::from_moves(200));
+        assert_eq!(gun.compatible_magazines(), vec!["stanag30"]);
+    }
+
+    #[test]
+    fn ammo_and_magazines() {
+        let ammo = item(serde_json::json!({
+            "type": "AMMO",
+            "id": "9mm_fmj",
+            "name": "9x19mm FMJ",
+            "ammo_type": "9mm",
+            "damage": { "values": [ { "damage_type": "bullet", "amount": 24 } ] },
+            "effects": [ "COOKOFF" ]
+        }));
+        assert_eq!(ammo.as_ammo().damage.units[0].amount, 24.0);
+        assert_eq!(ammo.as_ammo().effects, vec!["COOKOFF"]);
+
+        let magazine = item(serde_json::json!({
+            "type": "MAGAZINE",
+            "id": "glockmag",
+            "name": "Glock magazine",
+            "ammo_type": [ "9mm" ],
+            "pocket_data": [ { "pocket_type": "MAGAZINE", "ammo_restriction": { "9mm": 15 } } ]
+        }));
+        assert_eq!(magazine.as_magazine().capacity_for("9mm"), 15);
+        assert_eq!(magazine.as_magazine().capacity_for("45"), 0);
+        let old = item(serde_json::json!({
+            "type": "MAGAZINE",
+            "id": "oldmag",
+            "name": "old magazine",
+            "ammo_type": "9mm",
+            "capacity": 10,
+            "reload_time": 50
+        }));
+        assert_eq!(old.as_magazine().capacity_for("9mm"), 10);
+        assert_eq!(old.as_magazine().reload_time, Duration::from_moves(50));
+    }
+
+    #[test]
+    fn batch_time() {
+        let recipe: Recipe = serde_json::from_value(serde_json::json!({
+            "result": "soup",
+            "time": "10 m",
+            "components": [],
+            "batch_time_factors": [80, 2],
+        }))
+        .unwrap();
+        assert_eq!(recipe.batch_time(1), duration("10 m"));
+        // 10 m + 2 m 45.53 s
+        assert_eq!(recipe.batch_time(2), Duration::from_moves(76_553));
+        // about 16.8 m
+        assert_eq!(recipe.batch_time(4), Duration::from_moves(100_802));
+
+        let right_away = Recipe {
+            batch_time_factors: Some((50, 0)),
+            ..recipe
+        };
+        assert_eq!(right_away.batch_time(3), duration("20 m"));
+    }
+
+    #[test]
+    fn to_hit() {
+        let plain: ToHit = serde_json::from_value(serde_json::json!(2)).unwrap();
+        assert_eq!(plain.value(), 2);
+        let described: ToHit = serde_json::from_value(serde_json::json!({
+            "grip": "weapon",
+            "length": "long",
+            "surface": "line",
+            "balance": "good",
+        }))
+        .unwrap();
+        assert_eq!(described.value(), 2);
+        let cached = serde_json::to_value(&described).unwrap();
+        let described: ToHit = serde_json::from_value(cached).unwrap();
+        assert_eq!(described.value(), 2);
+    }
+
+    #[test]
+    fn books() {
+        let item: CataItem = serde_json::from_value(serde_json::json!({
+            "type": "BOOK",
+            "id": "mag_cooking",
+            "name": "cooking magazine",
+            "skill": "cooking",
+            "max_level": 2,
+            "time": 10,
+        }))
+        .unwrap();
+        assert_eq!(item.as_book().time, duration("10 m"));
+        let shown: Book =
+            serde_json::from_value(serde_json::to_value(item.as_book()).unwrap()).unwrap();
+        assert_eq!(shown.time, duration("10 m"));
+
+        let recipe = |book_learn| -> Recipe {
+            serde_json::from_value(serde_json::json!({
+                "result": "soup",
+                "book_learn": book_learn,
+            }))
+            .unwrap()
+        };
+        let taught = vec![("cookbook".to_string(), 2), ("mag_cooking".to_string(), 0)];
+        let listed = recipe(serde_json::json!([
+            ["cookbook", 2],
+            ["mag_cooking", 0, "soup"]
+        ]));
+        assert_eq!(listed.book_learn, taught);
+        let described = recipe(serde_json::json!({
+            "cookbook": { "skill_level": 2 },
+            "mag_cooking": { "hidden": true },
+        }));
+        assert_eq!(described.book_learn, taught);
+    }
+
+    #[test]
+    fn decomp_learn() {
+        let recipe = |decomp_learn| -> Recipe {
+            serde_json::from_value(serde_json::json!({
+                "result": "radio",
+                "decomp_learn": decomp_learn,
+            }))
+            .unwrap()
+        };
+        assert!(matches!(
+            recipe(serde_json::json!(2)).decomp_learn,
+            Some(DecompLearn::Level(2))
+        ));
+        match recipe(serde_json::json!([["electronics", 3]])).decomp_learn {
+            Some(DecompLearn::Skills(skills)) => {
+                assert_eq!(skills, vec![("electronics".to_string(), 3)])
+            }
+            other => panic!("{:?}", other),
+        }
+    }
+
+    #[test]
+    fn proficiencies() {
+        let recipe: Recipe = serde_json::from_value(serde_json::json!({
+            "result": "boots",
+            "proficiencies": [
+                { "proficiency": "prof_cobbling", "max_experience": "2 h" },
+                { "proficiency": "prof_leatherworking", "required": true, "time_multiplier": 1.5 },
+            ],
+        }))
+        .unwrap();
+        let cobbling = &recipe.proficiencies[0];
+        let leatherworking = &recipe.proficiencies[1];
+        assert!(!cobbling.required);
+        assert_eq!(cobbling.learning_time_multiplier, 1.0);
+        assert_eq!(cobbling.max_experience, Some(duration("2 h")));
+        assert!(leatherworking.required);
+        assert_eq!(leatherworking.time_multiplier, Some(1.5));
+
+        let proficiency: Proficiency = serde_json::from_value(serde_json::json!({
+            "id": "prof_cobbling",
+            "name": { "str": "Cobbling" },
+            "time_to_learn": "10 h",
+            "required_proficiencies": ["prof_leatherworking"],
+        }))
+        .unwrap();
+        assert!(proficiency.can_learn);
+        assert_eq!(proficiency.default_time_multiplier, 2.0);
+        assert_eq!(proficiency.time_to_learn, duration("10 h"));
+    }
+}
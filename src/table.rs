@@ -48,12 +48,12 @@ impl Table {
             for (i, h) in self.headers.iter().enumerate() {
                 let w = column_widths[i];
                 offset += w;
-                if offset > self.line_width{
+                if offset > self.line_width {
                     offset = 0;
                     rows.push(cells.join(" "));
                     cells.clear();
                 }
-                
+
                 cells.push(format!("{h:>w$}", h = h, w = w));
             }
             rows.push(cells.join(" "));
@@ -65,7 +65,7 @@ impl Table {
             for (i, c) in row.cells.iter().enumerate() {
                 let w = column_widths[i];
                 offset += w;
-                if offset > self.line_width{
+                if offset > self.line_width {
                     offset = 0;
                     rows.push(cells.join(" "));
                     cells.clear();
@@ -76,9 +76,8 @@ impl Table {
             rows.push(cells.join(" "))
         }
         rows.push(rows[0].clone());
-        let out = rows.join("\n");
 
-        out
+        rows.join("\n")
     }
 }
 
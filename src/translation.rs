@@ -0,0 +1,114 @@
+// The game's gettext catalogs, lang/mo/<locale>/LC_MESSAGES/cataclysm-dda.mo.
+// Only what names need: singular translations, with or without context.
+use std::{collections::HashMap, convert::TryInto, path::Path, sync::OnceLock};
+
+const MO_MAGIC: u32 = 0x9504_12de;
+// separates the context from the msgid in a catalog key
+const CONTEXT_SEPARATOR: char = '\u{4}';
+
+static CATALOG: OnceLock<Catalog> = OnceLock::new();
+
+pub(crate) struct Catalog {
+    // msgid (prefixed with the context, if any) -> translation
+    messages: HashMap<String, String>,
+}
+
+impl Catalog {
+    pub(crate) fn load(cata_root: &Path, locale: &str) -> Result<Catalog, String> {
+        let mo_root = cata_root.join("lang").join("mo");
+        let path = mo_root
+            .join(locale)
+            .join("LC_MESSAGES")
+            .join("cataclysm-dda.mo");
+        let data = match std::fs::read(&path) {
+            Ok(data) => data,
+            Err(e) => {
+                let mut known: Vec<String> = std::fs::read_dir(&mo_root)
+                    .into_iter()
+                    .flatten()
+                    .filter_map(|e| e.ok())
+                    .map(|e| e.file_name().to_string_lossy().into_owned())
+                    .collect();
+                known.sort();
+                return Err(format!(
+                    "no translations for {:?} ({}: {}); available languages: {:?}",
+                    locale,
+                    path.display(),
+                    e,
+                    known
+                ));
+            }
+        };
+        Catalog::parse(&data).map_err(|e| format!("{}: {}", path.display(), e))
+    }
+
+    // https://www.gnu.org/software/gettext/manual/html_node/MO-Files.html
+    fn parse(data: &[u8]) -> Result<Catalog, String> {
+        let word = |at: usize, big_endian: bool| -> Result<u32, String> {
+            let bytes: [u8; 4] = data
+                .get(at..at + 4)
+                .and_then(|b| b.try_into().ok())
+                .ok_or_else(|| format!("truncated at byte {}", at))?;
+            Ok(if big_endian {
+                u32::from_be_bytes(bytes)
+            } else {
+                u32::from_le_bytes(bytes)
+            })
+        };
+        let big_endian = match word(0, false)? {
+            MO_MAGIC => false,
+            x if x.swap_bytes() == MO_MAGIC => true,
+            x => return Err(format!("not a .mo file (magic {:#x})", x)),
+        };
+        let count = word(8, big_endian)? as usize;
+        let originals = word(12, big_endian)? as usize;
+        let translations = word(16, big_endian)? as usize;
+        // the table entries are (length, offset) pairs
+        let string_at = |table: usize, i: usize| -> Result<&str, String> {
+            let len = word(table + 8 * i, big_endian)? as usize;
+            let offset = word(table + 8 * i + 4, big_endian)? as usize;
+            let bytes = data
+                .get(offset..offset + len)
+                .ok_or_else(|| format!("string {} is out of bounds", i))?;
+            std::str::from_utf8(bytes).map_err(|e| format!("string {}: {}", i, e))
+        };
+
+        let mut messages = HashMap::with_capacity(count);
+        for i in 0..count {
+            // plurals are "singular\0plural" and "form 0\0form 1\0...", we only want the first ones
+            let original = string_at(originals, i)?.split('\0').next().unwrap();
+            let translation = string_at(translations, i)?.split('\0').next().unwrap();
+            // the empty msgid is the catalog's header
+            if original.is_empty() || translation.is_empty() {
+                continue;
+            }
+            messages.insert(original.to_string(), translation.to_string());
+        }
+        Ok(Catalog { messages })
+    }
+
+    fn get(&self, context: Option<&str>, msgid: &str) -> Option<&str> {
+        let translation = match context {
+            Some(context) => {
+                let key = format!("{}{}{}", context, CONTEXT_SEPARATOR, msgid);
+                self.messages.get(&key)
+            }
+            None => self.messages.get(msgid),
+        };
+        translation.map(|t| t.as_str())
+    }
+}
+
+// Once set, names are shown in this language wherever a translation exists.
+pub(crate) fn set_catalog(catalog: Catalog) {
+    if CATALOG.set(catalog).is_err() {
+        panic!("translations are already loaded");
+    }
+}
+
+pub(crate) fn translate<'a>(context: Option<&str>, msgid: &'a str) -> &'a str {
+    CATALOG
+        .get()
+        .and_then(|catalog| catalog.get(context, msgid))
+        .unwrap_or(msgid)
+}